@@ -0,0 +1,45 @@
+use anyhow::{bail, Result};
+use clap::Subcommand;
+
+use crate::core::Config;
+use crate::library::Library;
+
+#[derive(Subcommand)]
+pub enum DbAction {
+    /// Compact the database and optimise the caption search index
+    Vacuum,
+    /// Check the database and search index for corruption
+    Check,
+    /// Re-sync the library with the files in the download directory
+    Rebuild,
+}
+
+pub fn run(config: &Config, action: DbAction) -> Result<()> {
+    let mut library = Library::open(&config.library_path())?;
+
+    match action {
+        DbAction::Vacuum => {
+            library.vacuum()?;
+            println!("Library compacted: {}", config.library_path().display());
+        }
+        DbAction::Check => {
+            let problems = library.integrity_check()?;
+            if !problems.is_empty() {
+                for problem in &problems {
+                    eprintln!("{}", problem);
+                }
+                bail!("Library integrity check found {} problem(s)", problems.len());
+            }
+            println!("Library OK");
+        }
+        DbAction::Rebuild => {
+            let report = library.rebuild(&config.output_dir, &[&config.gallery_dir(), &config.staging_dir()])?;
+            println!(
+                "Library rebuilt: {} file(s) added, {} missing file(s) removed",
+                report.added, report.removed
+            );
+        }
+    }
+
+    Ok(())
+}
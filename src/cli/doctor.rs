@@ -0,0 +1,31 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::core::Config;
+use crate::diagnostics;
+
+#[derive(Args)]
+pub struct DoctorArgs {
+    /// Also write the report to this file
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(config: &Config, args: DoctorArgs) -> Result<()> {
+    eprintln!("Checking, this can take a few seconds…");
+    let report = diagnostics::run(config);
+    let text = report.redacted();
+    print!("{}", text);
+
+    if let Some(path) = &args.output {
+        fs::write(path, &text).with_context(|| format!("Failed to write {}", path.display()))?;
+        eprintln!("Saved the report to {}", path.display());
+    }
+
+    match report.failures() {
+        0 => Ok(()),
+        failures => bail!("{} check(s) failed", failures),
+    }
+}
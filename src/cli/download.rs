@@ -0,0 +1,182 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use crate::core::schedule::{format_countdown, TimeWindow};
+use crate::core::disk::format_size;
+use crate::core::{shutdown, storage, Config};
+use crate::downloader::error::QueueInUse;
+use crate::downloader::queue::{DownloadQueue, JobOutcome, QueueEvent};
+use crate::downloader::InstagramDownloader;
+use crate::gallery::Gallery;
+use crate::ipc;
+use crate::library::Library;
+use crate::ui::app::{Download, DownloadStatus};
+
+#[derive(Args)]
+pub struct DownloadArgs {
+    /// Instagram post or reel URLs
+    #[arg(required = true)]
+    urls: Vec<String>,
+    /// Download again even if the library already has the file
+    #[arg(long)]
+    force: bool,
+    /// Only start the downloads within this daily window, e.g. 01:00-06:00
+    #[arg(long)]
+    window: Option<TimeWindow>,
+}
+
+/// Hands the URLs to a running instance if there is one, otherwise
+/// downloads them here, accepting URLs from later invocations meanwhile
+pub fn run(config: &Config, args: DownloadArgs) -> Result<()> {
+    if forward(config, &args)? {
+        return Ok(());
+    }
+
+    if let Some(warning) = config.settings.tls.warning() {
+        eprintln!("⚠️  {}", warning);
+    }
+    let mut library = Library::open(&config.library_path())?;
+    let mut queue = DownloadQueue::new(InstagramDownloader::new(config.clone())?);
+    match queue.restore(config.queue_path(), &mut library) {
+        Ok(restored) => {
+            if let Some(summary) = restored.summary() {
+                println!("{}", summary);
+            }
+        }
+        // Whoever runs the queue may have started listening since we asked
+        Err(e) if e.is::<QueueInUse>() => {
+            if forward(config, &args)? {
+                return Ok(());
+            }
+            bail!("{}; close it first", e);
+        }
+        Err(e) => return Err(e),
+    }
+    let listener = ipc::listen(config)?;
+
+    for url in args.urls {
+        if queue.enqueue_unless_downloaded(&mut library, url.clone(), args.force, args.window)?.is_none() {
+            println!("Skipped, already downloaded or queued: {}", url);
+        }
+    }
+
+    // Stop the running download on Ctrl+C rather than leave yt-dlp behind
+    shutdown::install();
+    let mut failures = 0;
+    let mut completed = 0;
+    let mut waiting_until = None;
+    let mut throttled = false;
+    let mut low_on_space = false;
+    loop {
+        for forwarded in listener.iter().flat_map(|listener| listener.poll()) {
+            let queued = queue.enqueue_unless_downloaded(&mut library, forwarded.url.clone(), forwarded.force, forwarded.window);
+            println!("Received from another invocation: {}", forwarded.url);
+            forwarded.respond(matches!(queued, Ok(Some(_))));
+        }
+
+        for event in queue.poll() {
+            match event {
+                QueueEvent::Started => {
+                    if let Some(job) = queue.current() {
+                        println!("⬇️  {}", job.url);
+                    }
+                }
+                QueueEvent::Progress(DownloadStatus::Downloading { progress, speed, eta, .. }) => {
+                    eprint!("\r   {:5.1}%  {}  ETA {}   ", progress * 100.0, speed, eta);
+                    let _ = io::stderr().flush();
+                }
+                QueueEvent::Progress(_) => {}
+                QueueEvent::HookFailed(message) => eprintln!("⚠️  {}", message),
+                QueueEvent::Retrying { message, .. } => {
+                    eprintln!();
+                    println!("🔁 {}, retrying", message);
+                }
+                QueueEvent::Finished { job, outcome, duration } => {
+                    eprintln!();
+                    for download in Download::from_outcome(&job, &outcome, duration) {
+                        library.record_attempt(&download)?;
+                    }
+                    match &outcome {
+                        JobOutcome::Completed { files } => {
+                            completed += 1;
+                            for info in files {
+                                println!("✅ {}", info.filepath);
+                            }
+                            outcome.warnings().iter().for_each(|warning| eprintln!("⚠️  {}", warning));
+                            let paths: Vec<String> = files.iter().map(|info| info.filepath.clone()).collect();
+                            let keep = library.media_file_ids(&paths)?;
+                            if let Some(summary) = storage::enforce_quota(&config.settings.storage, &mut library, &keep)?.summary() {
+                                println!("🧹 {}", summary);
+                            }
+                        }
+                        JobOutcome::Failed { message, .. } => {
+                            failures += 1;
+                            println!("❌ {}", message);
+                        }
+                        JobOutcome::Cancelled => println!("Cancelled"),
+                    }
+                }
+            }
+        }
+
+        if queue.current().is_none() && queue.len() == 0 {
+            break;
+        }
+        if shutdown::requested() {
+            eprintln!();
+            bail!("Interrupted with {} download(s) unfinished", queue.len() + queue.current().map_or(0, |_| 1));
+        }
+
+        let waiting = queue.waiting_until();
+        if waiting != waiting_until {
+            if let Some(until) = waiting {
+                println!(
+                    "⏸️  Waiting until {} ({})",
+                    until.format("%H:%M"),
+                    format_countdown(until, chrono::Local::now())
+                );
+            }
+            waiting_until = waiting;
+        }
+
+        let wait = queue.throttled_for();
+        if let (Some(wait), false) = (wait, throttled) {
+            println!("🐢 Rate limited, next job in {}s", wait.as_secs() + 1);
+        }
+        throttled = wait.is_some();
+
+        let space = queue.low_on_space();
+        if let (Some((free, needed)), false) = (space, low_on_space) {
+            println!("💾 Only {} free, waiting until there is {}", format_size(free), format_size(needed));
+        }
+        low_on_space = space.is_some();
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    if completed > 0 {
+        let gallery = Gallery::new(config.gallery_dir());
+        gallery.generate(&library.gallery_posts(&config.output_dir, &[gallery.root()])?, false)?;
+    }
+    if failures > 0 {
+        bail!("{} download(s) failed", failures);
+    }
+    Ok(())
+}
+
+/// Hands the URLs to a running instance; `false` if none is listening
+fn forward(config: &Config, args: &DownloadArgs) -> Result<bool> {
+    let Some(replies) = ipc::forward(config, &args.urls, args.force, args.window)? else {
+        return Ok(false);
+    };
+    for reply in replies {
+        if reply.queued {
+            println!("Queued in running instance: {}", reply.url);
+        } else {
+            println!("Skipped by running instance, already downloaded or queued: {}", reply.url);
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,61 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{Days, Local, NaiveDate};
+use clap::Args;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::PathBuf;
+
+use crate::core::history::{DateRange, HistoryFilter};
+use crate::core::Config;
+use crate::export::{self, ExportFormat};
+use crate::library::Library;
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "csv")]
+    format: ExportFormat,
+    /// Only include attempts on or after this day (YYYY-MM-DD)
+    #[arg(long)]
+    since: Option<NaiveDate>,
+    /// Only include attempts on or before this day (YYYY-MM-DD)
+    #[arg(long)]
+    until: Option<NaiveDate>,
+    /// Shortcut for --since N days ago, e.g. 7 for a weekly report
+    #[arg(long, conflicts_with = "since")]
+    last_days: Option<u32>,
+    /// Write to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(config: &Config, args: ExportArgs) -> Result<()> {
+    let library = Library::open(&config.library_path())?;
+
+    let since = match args.last_days {
+        Some(days) => Some(
+            Local::now()
+                .date_naive()
+                .checked_sub_days(Days::new(days.into()))
+                .ok_or_else(|| anyhow!("--last-days {} reaches back before the earliest date supported", days))?,
+        ),
+        None => args.since,
+    };
+    let filter = HistoryFilter {
+        date: DateRange::days(since, args.until),
+        ascending: true,
+        ..HistoryFilter::default()
+    };
+    let downloads = library.history(&filter)?;
+
+    match &args.output {
+        Some(path) => {
+            let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+            export::export(&downloads, args.format, &mut BufWriter::new(file))?;
+            eprintln!("Exported {} record(s) to {}", downloads.len(), path.display());
+        }
+        None => export::export(&downloads, args.format, &mut io::stdout().lock())?,
+    }
+
+    Ok(())
+}
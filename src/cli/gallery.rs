@@ -0,0 +1,31 @@
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+
+use crate::core::Config;
+use crate::gallery::Gallery;
+use crate::library::Library;
+
+#[derive(Args)]
+pub struct GalleryArgs {
+    /// Write the site here instead of the gallery folder in the download directory
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Rewrite every page and thumbnail, not just those that changed
+    #[arg(long)]
+    full: bool,
+}
+
+pub fn run(config: &Config, args: GalleryArgs) -> Result<()> {
+    let library = Library::open(&config.library_path())?;
+    let gallery = Gallery::new(args.output.unwrap_or_else(|| config.gallery_dir()));
+
+    let report = gallery.generate(&library.gallery_posts(&config.output_dir, &[gallery.root()])?, args.full)?;
+    println!(
+        "Gallery updated: {} post(s), {} page(s) written, {} removed",
+        report.posts, report.written, report.removed
+    );
+    println!("Open {}", gallery.root().join("index.html").display());
+
+    Ok(())
+}
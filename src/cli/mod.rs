@@ -0,0 +1,49 @@
+pub mod db;
+pub mod doctor;
+pub mod download;
+pub mod export;
+pub mod gallery;
+pub mod native_host;
+
+use clap::{Parser, Subcommand};
+
+#[derive(Parser)]
+#[command(name = "instadown", version, about = "An Instagram video downloader application")]
+pub struct Cli {
+    /// Run the TUI against a running `instadown serve` instead of downloading in-process
+    #[arg(long)]
+    pub attach: bool,
+    /// Pick up Instagram links copied to the clipboard, whatever `config.toml` says
+    #[arg(long)]
+    pub watch_clipboard: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Maintain the media library database
+    Db {
+        #[command(subcommand)]
+        action: db::DbAction,
+    },
+    /// Check the setup and print a report that is safe to share
+    Doctor(doctor::DoctorArgs),
+    /// Export download history as CSV, JSON Lines or an HTML report
+    Export(export::ExportArgs),
+    /// Download URLs, handing them to the running instance if there is one
+    Download(download::DownloadArgs),
+    /// Run the download queue as a daemon with a local HTTP API
+    Serve {
+        /// Port on 127.0.0.1 to listen on; 0 picks a free one
+        #[arg(short, long, default_value_t = 7337)]
+        port: u16,
+    },
+    /// Generate a static HTML gallery of the downloaded posts
+    Gallery(gallery::GalleryArgs),
+    /// Browser extension native messaging host; without a subcommand, run it
+    NativeHost {
+        #[command(subcommand)]
+        action: Option<native_host::NativeHostAction>,
+    },
+}
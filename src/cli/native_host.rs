@@ -0,0 +1,54 @@
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use std::fs;
+
+use crate::core::Config;
+use crate::native::{self, manifest::Browser, HOST_NAME};
+
+#[derive(Subcommand)]
+pub enum NativeHostAction {
+    /// Print the host manifest JSON
+    Manifest {
+        #[arg(short, long, value_enum)]
+        browser: Browser,
+        /// Id of the extension allowed to talk to instadown
+        #[arg(long)]
+        extension_id: String,
+    },
+    /// Write the host manifest where the browser looks for it
+    Install {
+        #[arg(short, long, value_enum)]
+        browser: Browser,
+        /// Id of the extension allowed to talk to instadown
+        #[arg(long)]
+        extension_id: String,
+    },
+}
+
+pub fn run(config: &Config, action: Option<NativeHostAction>) -> Result<()> {
+    let Some(action) = action else {
+        return native::run_host(config);
+    };
+
+    let executable = std::env::current_exe()
+        .and_then(|exe| exe.canonicalize())
+        .context("Cannot find the instadown executable")?;
+
+    match action {
+        NativeHostAction::Manifest { browser, extension_id } => {
+            let manifest = browser.manifest(&executable, &extension_id);
+            println!("{}", serde_json::to_string_pretty(&manifest)?);
+        }
+        NativeHostAction::Install { browser, extension_id } => {
+            let manifest = browser.manifest(&executable, &extension_id);
+            let dir = browser.manifest_dir()?;
+            let path = dir.join(format!("{}.json", HOST_NAME));
+            fs::create_dir_all(&dir)?;
+            fs::write(&path, serde_json::to_string_pretty(&manifest)?)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            println!("Installed native messaging host manifest at {}", path.display());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,320 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::downloader::utils::DownloadUtils;
+
+/// Where the clipboard's text comes from
+pub trait ClipboardProvider: Send {
+    fn name(&self) -> &str;
+
+    /// The current clipboard text; `None` when it is empty or not text
+    fn read(&mut self) -> Result<Option<String>>;
+}
+
+/// Reads the clipboard through a helper program such as `wl-paste`,
+/// using the first of `candidates` that is installed
+pub struct CommandProvider {
+    name: &'static str,
+    candidates: &'static [&'static [&'static str]],
+}
+
+impl CommandProvider {
+    pub fn wayland() -> Self {
+        Self {
+            name: "Wayland",
+            candidates: &[&["wl-paste", "--no-newline", "--type", "text"]],
+        }
+    }
+
+    pub fn x11() -> Self {
+        Self {
+            name: "X11",
+            candidates: &[&["xclip", "-selection", "clipboard", "-o"], &["xsel", "--clipboard", "--output"]],
+        }
+    }
+
+    pub fn macos() -> Self {
+        Self {
+            name: "macOS",
+            candidates: &[&["pbpaste"]],
+        }
+    }
+}
+
+impl ClipboardProvider for CommandProvider {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn read(&mut self) -> Result<Option<String>> {
+        for candidate in self.candidates {
+            let output = match Command::new(candidate[0])
+                .args(&candidate[1..])
+                .stdin(Stdio::null())
+                .stderr(Stdio::null())
+                .output()
+            {
+                Ok(output) => output,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e).with_context(|| format!("Failed to run {}", candidate[0])),
+            };
+            // The helpers exit non-zero when nothing (or no text) is copied
+            if !output.status.success() {
+                return Ok(None);
+            }
+            return Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()));
+        }
+
+        let programs: Vec<&str> = self.candidates.iter().map(|c| c[0]).collect();
+        bail!("{} clipboard needs {} installed", self.name, programs.join(" or "))
+    }
+}
+
+/// Treats a file's contents as the clipboard; for scripting and testing
+pub struct FileProvider {
+    path: PathBuf,
+}
+
+impl FileProvider {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl ClipboardProvider for FileProvider {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    fn read(&mut self) -> Result<Option<String>> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => Ok(Some(text)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", self.path.display())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    /// Pick from the session: Wayland, then X11, or macOS
+    #[default]
+    Auto,
+    Wayland,
+    X11,
+    Macos,
+    File,
+}
+
+/// What to do with a newly copied link
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardAction {
+    #[default]
+    Enqueue,
+    /// Ask in the TUI first
+    Confirm,
+}
+
+/// The `[clipboard]` section of `config.toml`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClipboardConfig {
+    pub enabled: bool,
+    pub provider: ProviderKind,
+    /// File watched by the `file` provider
+    pub file: Option<PathBuf>,
+    pub action: ClipboardAction,
+    pub interval_ms: u64,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            provider: ProviderKind::Auto,
+            file: None,
+            action: ClipboardAction::Enqueue,
+            interval_ms: 500,
+        }
+    }
+}
+
+impl ClipboardConfig {
+    pub fn provider(&self) -> Result<Box<dyn ClipboardProvider>> {
+        let provider: Box<dyn ClipboardProvider> = match self.provider {
+            ProviderKind::Wayland => Box::new(CommandProvider::wayland()),
+            ProviderKind::X11 => Box::new(CommandProvider::x11()),
+            ProviderKind::Macos => Box::new(CommandProvider::macos()),
+            ProviderKind::File => {
+                let path = self.file.clone().context("The file clipboard provider needs `file` set")?;
+                Box::new(FileProvider::new(path))
+            }
+            ProviderKind::Auto if cfg!(target_os = "macos") => Box::new(CommandProvider::macos()),
+            ProviderKind::Auto if std::env::var_os("WAYLAND_DISPLAY").is_some() => Box::new(CommandProvider::wayland()),
+            ProviderKind::Auto if std::env::var_os("DISPLAY").is_some() => Box::new(CommandProvider::x11()),
+            ProviderKind::Auto => bail!("No clipboard found; set `provider` in the [clipboard] settings"),
+        };
+        Ok(provider)
+    }
+}
+
+pub enum ClipboardEvent {
+    /// Instagram links that showed up in the clipboard for the first time
+    Copied(Vec<String>),
+    Error(String),
+}
+
+/// Polls a provider on a background thread and reports links copied since
+/// it started. Whatever was on the clipboard beforehand is ignored.
+pub struct ClipboardWatcher {
+    rx: Receiver<ClipboardEvent>,
+    stop: Arc<AtomicBool>,
+    provider: String,
+}
+
+impl ClipboardWatcher {
+    pub fn start(mut provider: Box<dyn ClipboardProvider>, interval: Duration) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let name = provider.name().to_string();
+
+        let stopped = stop.clone();
+        thread::spawn(move || {
+            let mut last = provider.read().ok().flatten();
+            let mut seen: HashSet<String> = last
+                .as_deref()
+                .map(DownloadUtils::extract_instagram_urls)
+                .unwrap_or_default()
+                .into_iter()
+                .collect();
+            let mut last_error = None;
+
+            while !stopped.load(Ordering::Relaxed) {
+                thread::sleep(interval);
+
+                let text = match provider.read() {
+                    Ok(text) => {
+                        last_error = None;
+                        text
+                    }
+                    Err(e) => {
+                        // Report a failure once, not on every poll
+                        let message = format!("{:#}", e);
+                        if last_error.as_ref() != Some(&message) && tx.send(ClipboardEvent::Error(message.clone())).is_err() {
+                            break;
+                        }
+                        last_error = Some(message);
+                        continue;
+                    }
+                };
+                if text == last {
+                    continue;
+                }
+
+                let urls: Vec<String> = text
+                    .as_deref()
+                    .map(DownloadUtils::extract_instagram_urls)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|url| seen.insert(url.clone()))
+                    .collect();
+                last = text;
+                if !urls.is_empty() && tx.send(ClipboardEvent::Copied(urls)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self { rx, stop, provider: name }
+    }
+
+    pub fn provider(&self) -> &str {
+        &self.provider
+    }
+
+    pub fn poll(&self) -> Vec<ClipboardEvent> {
+        self.rx.try_iter().collect()
+    }
+}
+
+impl Drop for ClipboardWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// Waits for the next batch of events, or gives up after a few seconds
+    fn next_events(watcher: &ClipboardWatcher) -> Vec<ClipboardEvent> {
+        let started = Instant::now();
+        loop {
+            let events = watcher.poll();
+            if !events.is_empty() || started.elapsed() > Duration::from_secs(5) {
+                return events;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn copied(events: Vec<ClipboardEvent>) -> Vec<String> {
+        events
+            .into_iter()
+            .flat_map(|event| match event {
+                ClipboardEvent::Copied(urls) => urls,
+                ClipboardEvent::Error(message) => panic!("{}", message),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_each_new_link_once() {
+        let path = std::env::temp_dir().join(format!("instadown-clipboard-{}.txt", std::process::id()));
+        fs::write(&path, "https://www.instagram.com/p/OLD/").unwrap();
+        let watcher = ClipboardWatcher::start(Box::new(FileProvider::new(path.clone())), Duration::from_millis(10));
+        assert_eq!(watcher.provider(), "file");
+        thread::sleep(Duration::from_millis(50));
+
+        fs::write(&path, "see https://www.instagram.com/p/OLD/ and instagram.com/reel/NEW/").unwrap();
+        assert_eq!(copied(next_events(&watcher)), ["https://instagram.com/reel/NEW/"]);
+
+        // Copying something else and then the same link again adds nothing
+        fs::write(&path, "just text").unwrap();
+        thread::sleep(Duration::from_millis(50));
+        fs::write(&path, "instagram.com/reel/NEW/").unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert!(watcher.poll().is_empty());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reports_read_errors_once() {
+        // A directory can't be read as text
+        let path = std::env::temp_dir().join(format!("instadown-clipboard-dir-{}", std::process::id()));
+        fs::create_dir_all(&path).unwrap();
+        let watcher = ClipboardWatcher::start(Box::new(FileProvider::new(path.clone())), Duration::from_millis(10));
+
+        let events = next_events(&watcher);
+        assert!(matches!(events.as_slice(), [ClipboardEvent::Error(_)]));
+        thread::sleep(Duration::from_millis(50));
+        assert!(watcher.poll().is_empty());
+
+        fs::remove_dir(&path).unwrap();
+    }
+}
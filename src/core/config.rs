@@ -1,11 +1,89 @@
+use anyhow::Result;
 use std::path::PathBuf;
+use directories::ProjectDirs;
 
+use super::settings::Settings;
+
+#[derive(Clone)]
 pub struct Config {
     pub output_dir: PathBuf,
+    pub data_dir: PathBuf,
+    /// Per-session directory for the instance socket
+    pub runtime_dir: PathBuf,
+    /// Where `config.toml` lives
+    pub config_dir: PathBuf,
+    /// User settings from `config.toml`
+    pub settings: Settings,
 }
 
 impl Config {
-    pub fn new(output_dir: PathBuf) -> Self {
-        Self { output_dir }
+    /// Works out the app's directories and reads `config.toml`, if present
+    pub fn load(output_dir: PathBuf) -> Result<Self> {
+        let mut config = Self::with_defaults(output_dir);
+        config.settings = Settings::read(&config.config_path())?;
+        Ok(config)
+    }
+
+    /// The app's directories with default settings, for when `config.toml`
+    /// can't be read
+    pub fn with_defaults(output_dir: PathBuf) -> Self {
+        // Fall back to keeping app data next to the downloads when the
+        // platform has no notion of a per-user data directory
+        let dirs = ProjectDirs::from("", "", "instadown");
+        let data_dir = dirs
+            .as_ref()
+            .map(|dirs| dirs.data_dir().to_path_buf())
+            .unwrap_or_else(|| output_dir.clone());
+        // Only Linux has XDG_RUNTIME_DIR; elsewhere the data dir will do
+        let runtime_dir = dirs
+            .as_ref()
+            .and_then(|dirs| dirs.runtime_dir())
+            .map(|dir| dir.to_path_buf())
+            .unwrap_or_else(|| data_dir.clone());
+        let config_dir = dirs
+            .as_ref()
+            .map(|dirs| dirs.config_dir().to_path_buf())
+            .unwrap_or_else(|| data_dir.clone());
+
+        Self { output_dir, data_dir, runtime_dir, config_dir, settings: Settings::default() }
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.config_dir.join("config.toml")
     }
-} 
\ No newline at end of file
+
+    pub fn library_path(&self) -> PathBuf {
+        self.data_dir.join("library.db")
+    }
+
+    /// Port and token of the running `instadown serve`, if any
+    pub fn daemon_info_path(&self) -> PathBuf {
+        self.data_dir.join("daemon.json")
+    }
+
+    /// Netscape cookie jar handed to yt-dlp, filled by the browser extension
+    pub fn cookies_path(&self) -> PathBuf {
+        self.data_dir.join("cookies.txt")
+    }
+
+    /// Unix socket the running instance accepts forwarded URLs on
+    pub fn socket_path(&self) -> PathBuf {
+        self.runtime_dir.join("instadown.sock")
+    }
+
+    /// Journal of queued jobs a restarted TUI or daemon picks up again
+    pub fn queue_path(&self) -> PathBuf {
+        self.data_dir.join("queue.jsonl")
+    }
+
+    /// Where downloads are written until they are finished, inside the
+    /// output directory so moving them into place is an atomic rename
+    pub fn staging_dir(&self) -> PathBuf {
+        self.output_dir.join(".staging")
+    }
+
+    /// Where the static HTML gallery is generated
+    pub fn gallery_dir(&self) -> PathBuf {
+        self.output_dir.join("gallery")
+    }
+}
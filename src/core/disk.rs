@@ -0,0 +1,41 @@
+use std::path::Path;
+
+/// Bytes available to this user on the filesystem holding `path`, which
+/// need not exist yet. `None` where the platform can't say.
+pub fn free_space(path: &Path) -> Option<u64> {
+    // Ask about the nearest directory that exists
+    let existing = path.ancestors().find(|dir| dir.exists())?;
+    available(existing)
+}
+
+#[cfg(unix)]
+fn available(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is NUL-terminated and `stats` is a valid statvfs to fill in
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stats) } != 0 {
+        return None;
+    }
+    #[allow(clippy::unnecessary_cast)]
+    Some(stats.f_bavail as u64 * stats.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn available(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// "1.5 GiB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
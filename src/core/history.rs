@@ -0,0 +1,257 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Local, NaiveDate};
+
+#[derive(Default, Clone, Copy, PartialEq)]
+pub enum StatusFilter {
+    #[default]
+    All,
+    Completed,
+    Failed,
+    Cancelled,
+    Skipped,
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+pub enum DateRange {
+    #[default]
+    All,
+    Today,
+    LastWeek,
+    LastMonth,
+    /// Explicit bounds, e.g. from the command line; either end may be open
+    Between(Option<DateTime<Local>>, Option<DateTime<Local>>),
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    #[default]
+    Date,
+    Size,
+    Uploader,
+}
+
+/// What the History tab's prompt is asking for
+#[derive(Clone, Copy, PartialEq)]
+pub enum HistoryPrompt {
+    /// Narrows the list as the query is typed
+    Search,
+    /// An uploader's name, applied on Enter
+    Profile,
+    /// `FROM..TO`, applied on Enter
+    Dates,
+}
+
+/// Search, filter and sort settings for the History tab
+#[derive(Default)]
+pub struct HistoryFilter {
+    pub query: String,
+    pub status: StatusFilter,
+    pub date: DateRange,
+    pub profile: Option<String>,
+    pub sort: SortKey,
+    pub ascending: bool,
+}
+
+impl StatusFilter {
+    pub fn next(self) -> Self {
+        match self {
+            Self::All => Self::Completed,
+            Self::Completed => Self::Failed,
+            Self::Failed => Self::Cancelled,
+            Self::Cancelled => Self::Skipped,
+            Self::Skipped => Self::All,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+            Self::Skipped => "skipped",
+        }
+    }
+
+    /// Value of the library's `attempts.status` column to match, if any
+    pub fn kind(self) -> Option<&'static str> {
+        match self {
+            Self::All => None,
+            Self::Completed => Some("completed"),
+            Self::Failed => Some("failed"),
+            Self::Cancelled => Some("cancelled"),
+            Self::Skipped => Some("skipped_duplicate"),
+        }
+    }
+}
+
+impl DateRange {
+    pub fn next(self) -> Self {
+        match self {
+            Self::All => Self::Today,
+            Self::Today => Self::LastWeek,
+            Self::LastWeek => Self::LastMonth,
+            Self::LastMonth | Self::Between(..) => Self::All,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::All => "any time",
+            Self::Today => "today",
+            Self::LastWeek => "last 7 days",
+            Self::LastMonth => "last 30 days",
+            Self::Between(..) => "custom range",
+        }
+    }
+
+    /// Earliest timestamp included in the range
+    pub fn since(self) -> Option<DateTime<Local>> {
+        let now = Local::now();
+        match self {
+            Self::All => None,
+            Self::Today => now
+                .date_naive()
+                .and_hms_opt(0, 0, 0)
+                .and_then(|midnight| midnight.and_local_timezone(Local).earliest()),
+            Self::LastWeek => Some(now - Duration::days(7)),
+            Self::LastMonth => Some(now - Duration::days(30)),
+            Self::Between(since, _) => since,
+        }
+    }
+
+    /// Whole days from `since` up to and including `until`; either may be open
+    pub fn days(since: Option<NaiveDate>, until: Option<NaiveDate>) -> Self {
+        Self::Between(
+            since.and_then(start_of_day),
+            until.and_then(|day| day.succ_opt()).and_then(start_of_day),
+        )
+    }
+
+    /// Parses `2024-01-01..2024-01-31`, `2024-01-01..`, `..2024-01-31` or a
+    /// single day. An empty range means any time.
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(Self::All);
+        }
+        let (since, until) = text.split_once("..").unwrap_or((text, text));
+        let day = |day: &str| -> Result<Option<NaiveDate>> {
+            let day = day.trim();
+            if day.is_empty() {
+                return Ok(None);
+            }
+            NaiveDate::parse_from_str(day, "%Y-%m-%d")
+                .map(Some)
+                .with_context(|| format!("\"{}\" isn't a YYYY-MM-DD date", day))
+        };
+        let (since, until) = (day(since)?, day(until)?);
+        if let (Some(since), Some(until)) = (since, until) {
+            if since > until {
+                bail!("{} is after {}", since, until);
+            }
+        }
+        Ok(Self::days(since, until))
+    }
+
+    /// The range as `parse` accepts it, to edit an existing filter
+    pub fn as_input(self) -> String {
+        let day = |bound: Option<DateTime<Local>>| bound.map(|at| at.format("%Y-%m-%d").to_string()).unwrap_or_default();
+        match self {
+            Self::All => String::new(),
+            Self::Between(since, until) => {
+                format!("{}..{}", day(since), day(until.map(|until| until - Duration::days(1))))
+            }
+            _ => format!("{}..", day(self.since())),
+        }
+    }
+
+    /// Exclusive upper bound, only set for explicit ranges
+    pub fn until(self) -> Option<DateTime<Local>> {
+        match self {
+            Self::Between(_, until) => until,
+            _ => None,
+        }
+    }
+}
+
+impl SortKey {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Date => Self::Size,
+            Self::Size => Self::Uploader,
+            Self::Uploader => Self::Date,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Date => "date",
+            Self::Size => "size",
+            Self::Uploader => "uploader",
+        }
+    }
+}
+
+impl HistoryFilter {
+    pub fn is_active(&self) -> bool {
+        !self.query.is_empty()
+            || self.status != StatusFilter::All
+            || self.date != DateRange::All
+            || self.profile.is_some()
+    }
+
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.query.is_empty() {
+            parts.push(format!("\"{}\"", self.query));
+        }
+        if self.status != StatusFilter::All {
+            parts.push(self.status.label().to_string());
+        }
+        if self.date != DateRange::All {
+            parts.push(match self.date {
+                DateRange::Between(..) => self.date.as_input(),
+                date => date.label().to_string(),
+            });
+        }
+        if let Some(profile) = &self.profile {
+            parts.push(format!("@{}", profile));
+        }
+        parts.push(format!(
+            "by {} {}",
+            self.sort.label(),
+            if self.ascending { "↑" } else { "↓" }
+        ));
+        parts.join(" · ")
+    }
+}
+
+fn start_of_day(day: NaiveDate) -> Option<DateTime<Local>> {
+    day.and_hms_opt(0, 0, 0)?.and_local_timezone(Local).earliest()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn parses_date_ranges() {
+        assert!(DateRange::parse("").unwrap() == DateRange::All);
+        let range = DateRange::parse("2024-01-01..2024-01-31").unwrap();
+        assert!(range == DateRange::days(Some(day("2024-01-01")), Some(day("2024-01-31"))));
+        assert_eq!(range.as_input(), "2024-01-01..2024-01-31");
+        assert_eq!(DateRange::parse("..2024-01-31").unwrap().as_input(), "..2024-01-31");
+        assert_eq!(DateRange::parse("2024-03-05").unwrap().as_input(), "2024-03-05..2024-03-05");
+    }
+
+    #[test]
+    fn rejects_bad_date_ranges() {
+        assert!(DateRange::parse("2024-02-01..2024-01-01").is_err());
+        assert!(DateRange::parse("yesterday..").is_err());
+    }
+}
@@ -1,3 +1,10 @@
 pub mod config;
+pub mod disk;
+pub mod history;
+pub mod platform;
+pub mod schedule;
+pub mod settings;
+pub mod shutdown;
+pub mod storage;
 
-pub use config::Config; 
\ No newline at end of file
+pub use config::Config;
@@ -0,0 +1,52 @@
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Opens a file with the desktop's default application.
+pub fn open_path(path: &Path) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+
+    spawn_detached(command.arg(path))
+}
+
+/// Shows the file in the system file manager, selecting it where supported.
+pub fn reveal_path(path: &Path) -> Result<()> {
+    if cfg!(target_os = "macos") {
+        spawn_detached(Command::new("open").arg("-R").arg(path))
+    } else if cfg!(target_os = "windows") {
+        spawn_detached(Command::new("explorer").arg(format!("/select,{}", path.display())))
+    } else {
+        let folder = path.parent().unwrap_or(Path::new("."));
+        spawn_detached(Command::new("xdg-open").arg(folder))
+    }
+}
+
+/// Copies text to the clipboard with an OSC 52 escape sequence, which
+/// the terminal forwards to the system clipboard (works over SSH too).
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+pub fn spawn_detached(command: &mut Command) -> Result<()> {
+    // Keep the child from scribbling over the TUI or the browser's pipe
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {:?}", command.get_program()))?;
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A daily stretch of time such as `09:00-17:00`. It wraps past midnight
+/// when the end is earlier than the start (`22:00-06:00`), and equal ends
+/// mean the whole day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeWindow {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl TimeWindow {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// `now` if the window is open, otherwise when it next opens
+    pub fn next_open(&self, now: DateTime<Local>) -> DateTime<Local> {
+        if self.contains(now.time()) {
+            return now;
+        }
+        let today = at(now.date_naive(), self.start);
+        if today > now {
+            today
+        } else {
+            at(now.date_naive() + Duration::days(1), self.start)
+        }
+    }
+
+    /// When the window that `now` falls in closes
+    pub fn closes_at(&self, now: DateTime<Local>) -> DateTime<Local> {
+        let today = at(now.date_naive(), self.end);
+        if today > now {
+            today
+        } else {
+            at(now.date_naive() + Duration::days(1), self.end)
+        }
+    }
+}
+
+/// `time` on `date`, stepping past a gap left by a DST change
+fn at(date: NaiveDate, time: NaiveTime) -> DateTime<Local> {
+    let naive = date.and_time(time);
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .or_else(|| Local.from_local_datetime(&(naive + Duration::hours(1))).earliest())
+        .unwrap_or_else(Local::now)
+}
+
+impl FromStr for TimeWindow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| anyhow!("Expected a window like 22:00-06:00, got {:?}", s))?;
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M").with_context(|| format!("{:?} is not an HH:MM time", time.trim()))
+        };
+        Ok(Self { start: parse(start)?, end: parse(end)? })
+    }
+}
+
+impl TryFrom<String> for TimeWindow {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<TimeWindow> for String {
+    fn from(window: TimeWindow) -> Self {
+        window.to_string()
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
+/// The `[schedule]` section of `config.toml`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScheduleConfig {
+    /// No job starts inside these windows; a running one is left to finish
+    pub quiet_hours: Vec<TimeWindow>,
+}
+
+impl ScheduleConfig {
+    /// Earliest time from `now` on that is outside quiet hours and, if the
+    /// job has one, inside its window
+    pub fn ready_at(&self, window: Option<TimeWindow>, now: DateTime<Local>) -> DateTime<Local> {
+        let mut time = now;
+        // Each step jumps to a window edge, so a handful always settles it
+        // unless quiet hours cover the job's whole window
+        for _ in 0..16 {
+            if let Some(quiet) = self.quiet_hours.iter().find(|quiet| quiet.contains(time.time())) {
+                time = quiet.closes_at(time);
+            } else if let Some(window) = window.filter(|window| !window.contains(time.time())) {
+                time = window.next_open(time);
+            } else {
+                return time;
+            }
+        }
+        time
+    }
+}
+
+/// "1h 05m 09s"
+pub fn format_countdown(until: DateTime<Local>, now: DateTime<Local>) -> String {
+    let seconds = (until - now).num_seconds().max(0);
+    if seconds >= 3600 {
+        format!("{}h {:02}m {:02}s", seconds / 3600, seconds % 3600 / 60, seconds % 60)
+    } else {
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(text: &str) -> TimeWindow {
+        text.parse().unwrap()
+    }
+
+    fn time(text: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(text, "%H:%M").unwrap()
+    }
+
+    fn on_june_3(hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 6, 3, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn windows_contain_their_start_but_not_their_end() {
+        let day = window("09:00-17:00");
+        assert!(day.contains(time("09:00")));
+        assert!(day.contains(time("16:59")));
+        assert!(!day.contains(time("17:00")));
+        assert!(!day.contains(time("08:59")));
+    }
+
+    #[test]
+    fn windows_wrap_past_midnight() {
+        let night = window("22:00-06:00");
+        assert!(night.contains(time("23:30")));
+        assert!(night.contains(time("05:59")));
+        assert!(!night.contains(time("06:00")));
+        assert!(!night.contains(time("12:00")));
+        assert!(window("00:00-00:00").contains(time("12:00")));
+    }
+
+    #[test]
+    fn parses_and_prints_windows() {
+        assert_eq!(window(" 1:00 - 6:30 ").to_string(), "01:00-06:30");
+        assert!("22:00".parse::<TimeWindow>().is_err());
+        assert!("25:00-06:00".parse::<TimeWindow>().is_err());
+    }
+
+    #[test]
+    fn jobs_wait_for_their_window() {
+        let schedule = ScheduleConfig::default();
+        let now = on_june_3(12, 0);
+        assert_eq!(schedule.ready_at(None, now), now);
+        assert_eq!(schedule.ready_at(Some(window("01:00-06:00")), now), Local.with_ymd_and_hms(2024, 6, 4, 1, 0, 0).unwrap());
+        assert_eq!(schedule.ready_at(Some(window("13:00-14:00")), now), on_june_3(13, 0));
+    }
+
+    #[test]
+    fn quiet_hours_hold_every_job() {
+        let schedule = ScheduleConfig { quiet_hours: vec![window("11:00-13:00")] };
+        assert_eq!(schedule.ready_at(None, on_june_3(12, 0)), on_june_3(13, 0));
+        // The job's window opens during quiet hours, so it starts when they end
+        assert_eq!(schedule.ready_at(Some(window("12:30-15:00")), on_june_3(10, 0)), on_june_3(13, 0));
+    }
+
+    #[test]
+    fn formats_countdowns() {
+        let now = on_june_3(12, 0);
+        assert_eq!(format_countdown(now + Duration::seconds(65), now), "1m 05s");
+        assert_eq!(format_countdown(now + Duration::seconds(3909), now), "1h 05m 09s");
+        assert_eq!(format_countdown(now - Duration::seconds(5), now), "0m 00s");
+    }
+}
@@ -0,0 +1,86 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use crate::clipboard::ClipboardConfig;
+use crate::diagnostics::DoctorConfig;
+use super::schedule::ScheduleConfig;
+use super::storage::StorageConfig;
+use crate::downloader::backend::YtDlpConfig;
+use crate::downloader::proxy::ProxyConfig;
+use crate::downloader::throttle::RateLimitConfig;
+use crate::downloader::tls::TlsConfig;
+use crate::downloader::user_agent::UserAgentConfig;
+use crate::downloader::verify::VerifyConfig;
+use crate::hooks::HookConfig;
+use crate::subscriptions::SubscriptionConfig;
+
+/// Everything in `config.toml`. Every section is optional.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub hooks: Vec<HookConfig>,
+    pub clipboard: ClipboardConfig,
+    pub subscriptions: Vec<SubscriptionConfig>,
+    pub schedule: ScheduleConfig,
+    pub rate_limit: RateLimitConfig,
+    pub proxy: ProxyConfig,
+    pub tls: TlsConfig,
+    pub user_agent: UserAgentConfig,
+    pub yt_dlp: YtDlpConfig,
+    pub storage: StorageConfig,
+    pub verify: VerifyConfig,
+    pub doctor: DoctorConfig,
+}
+
+impl Settings {
+    /// A missing file means defaults; a broken one is an error rather than
+    /// silently ignored settings
+    pub fn read(path: &Path) -> Result<Self> {
+        let settings: Self = match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).with_context(|| format!("Invalid settings in {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+
+        settings.validate().with_context(|| format!("Invalid settings in {}", path.display()))?;
+        Ok(settings)
+    }
+
+    /// Checks what the types alone can't, section by section
+    pub fn validate(&self) -> Result<()> {
+        for subscription in &self.subscriptions {
+            subscription.validate()?;
+        }
+        self.rate_limit.validate()?;
+        self.proxy.validate()?;
+        self.tls.validate()?;
+        self.user_agent.validate()?;
+        self.yt_dlp.validate()?;
+        self.storage.validate()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_every_section() {
+        let settings: Settings = toml::from_str("[storage]\nquota_mb = 0\n").unwrap();
+        assert!(settings.validate().unwrap_err().to_string().contains("quota_mb"));
+        let settings: Settings = toml::from_str("[storage]\nquota_mb = 10\n").unwrap();
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn names_the_file_once() {
+        let path = std::env::temp_dir().join(format!("instadown-settings-{}.toml", std::process::id()));
+        fs::write(&path, "[storage]\nquota_mb = 0\n").unwrap();
+        let error = format!("{:#}", Settings::read(&path).err().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.matches("Invalid settings in").count(), 1, "{}", error);
+    }
+}
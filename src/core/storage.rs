@@ -0,0 +1,286 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::disk::format_size;
+use crate::library::Library;
+
+const MIB: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionPolicy {
+    /// Delete the files downloaded longest ago
+    #[default]
+    DeleteOldest,
+    /// Move the files downloaded longest ago to `cold_storage`
+    ColdStorage,
+}
+
+/// The `[storage]` section of `config.toml`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// The queue pauses rather than leave less than this free
+    pub min_free_mb: u64,
+    /// Size assumed for a download until some have finished
+    pub job_estimate_mb: u64,
+    /// Most the downloads may take up; unlimited if unset
+    pub quota_mb: Option<u64>,
+    /// What happens to old downloads once over `quota_mb`
+    pub retention: RetentionPolicy,
+    /// Where `retention = "cold_storage"` moves them
+    pub cold_storage: Option<PathBuf>,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            min_free_mb: 1024,
+            job_estimate_mb: 100,
+            quota_mb: None,
+            retention: RetentionPolicy::DeleteOldest,
+            cold_storage: None,
+        }
+    }
+}
+
+impl StorageConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.quota_mb == Some(0) {
+            bail!("`quota_mb` must be at least 1");
+        }
+        match (self.retention, &self.cold_storage) {
+            (RetentionPolicy::ColdStorage, None) => bail!("`retention = \"cold_storage\"` needs a `cold_storage` directory"),
+            (RetentionPolicy::DeleteOldest, Some(_)) => bail!("`cold_storage` is only used with `retention = \"cold_storage\"`"),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn min_free(&self) -> u64 {
+        self.min_free_mb * MIB
+    }
+
+    pub fn job_estimate(&self) -> u64 {
+        self.job_estimate_mb * MIB
+    }
+
+    /// "keep 1.0 GiB free · quota 50.0 GiB, oldest deleted"
+    pub fn summary(&self) -> String {
+        let mut summary = format!("keep {} free", format_size(self.min_free()));
+        if let Some(quota) = self.quota_mb {
+            summary.push_str(&format!(" · quota {}", format_size(quota * MIB)));
+            summary.push_str(match self.retention {
+                RetentionPolicy::DeleteOldest => ", oldest deleted",
+                RetentionPolicy::ColdStorage => ", oldest moved to cold storage",
+            });
+        }
+        summary
+    }
+}
+
+/// What `enforce_quota` did to make room
+#[derive(Default)]
+pub struct Pruned {
+    pub files: usize,
+    pub bytes: u64,
+    pub moved: bool,
+}
+
+impl Pruned {
+    /// "Storage quota: deleted 3 old files (1.2 GiB)", or `None` if nothing
+    /// had to go
+    pub fn summary(&self) -> Option<String> {
+        (self.files > 0).then(|| {
+            format!(
+                "Storage quota: {} {} old file{} ({})",
+                if self.moved { "moved" } else { "deleted" },
+                self.files,
+                if self.files == 1 { "" } else { "s" },
+                format_size(self.bytes)
+            )
+        })
+    }
+}
+
+/// Deletes or moves away the oldest downloads until the rest fit in the
+/// quota, and records that in the library. The media files in `keep`, the
+/// ones just downloaded, are left alone.
+pub fn enforce_quota(config: &StorageConfig, library: &mut Library, keep: &[i64]) -> Result<Pruned> {
+    let mut pruned = Pruned { moved: config.retention == RetentionPolicy::ColdStorage, ..Pruned::default() };
+    let Some(quota) = config.quota_mb.map(|mb| mb * MIB) else {
+        return Ok(pruned);
+    };
+
+    // Files already in cold storage, or gone, don't count
+    let files: Vec<(i64, PathBuf, u64)> = library
+        .stored_files()?
+        .into_iter()
+        .filter(|(_, path)| !config.cold_storage.as_deref().is_some_and(|cold| Path::new(path).starts_with(cold)))
+        .filter_map(|(id, path)| fs::metadata(&path).ok().map(|meta| (id, PathBuf::from(path), meta.len())))
+        .collect();
+    let mut total: u64 = files.iter().map(|(_, _, size)| size).sum();
+
+    // Oldest first
+    for (id, path, size) in files.into_iter().filter(|(id, _, _)| !keep.contains(id)) {
+        if total <= quota {
+            break;
+        }
+        match &config.cold_storage {
+            Some(cold) if config.retention == RetentionPolicy::ColdStorage => {
+                let target = free_path(library, &path, cold)?;
+                move_file(&path, &target)?;
+                if let Err(e) = library.file_moved(id, &target.to_string_lossy()) {
+                    // Put the file back where the library still expects it
+                    let _ = move_file(&target, &path);
+                    return Err(e);
+                }
+            }
+            _ => {
+                fs::remove_file(&path).with_context(|| format!("Failed to delete {}", path.display()))?;
+                library.file_removed(id)?;
+            }
+        }
+        total -= size;
+        pruned.files += 1;
+        pruned.bytes += size;
+    }
+    Ok(pruned)
+}
+
+/// Where in `dir` to move `path` to: its own name, or "name (2).mp4" and
+/// so on when a file or a library row already has that
+fn free_path(library: &Library, path: &Path, dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+    for n in 1.. {
+        let name = if n == 1 { format!("{}{}", stem, extension) } else { format!("{} ({}){}", stem, n, extension) };
+        let target = dir.join(name);
+        if !target.exists() && !library.has_file(&target.to_string_lossy())? {
+            return Ok(target);
+        }
+    }
+    unreachable!()
+}
+
+/// Moves `from` to `to`, copying when they are on different filesystems.
+/// On failure `from` is left where it was.
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))?;
+    if let Err(e) = fs::remove_file(from) {
+        let _ = fs::remove_file(to);
+        return Err(e).with_context(|| format!("Failed to delete {}", from.display()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::app::{AttemptStatus, Download};
+    use chrono::{Local, TimeZone};
+
+    /// A 1 MiB download of `name` in `dir`, finished at `seconds`
+    fn download(library: &mut Library, dir: &Path, name: &str, seconds: i64) -> i64 {
+        let path = dir.join(name);
+        fs::write(&path, vec![0u8; MIB as usize]).unwrap();
+        let filename = path.to_string_lossy().to_string();
+        library
+            .record_attempt(&Download {
+                id: 0,
+                url: format!("https://www.instagram.com/p/{}/", seconds),
+                filename: filename.clone(),
+                timestamp: Local.timestamp_opt(seconds, 0).unwrap(),
+                status: AttemptStatus::Completed,
+                duration_ms: 0,
+                error: None,
+                uploader: None,
+                caption: None,
+                size: Some(MIB),
+                user_agent: None,
+                removed: false,
+                sha256: None,
+            })
+            .unwrap();
+        library.media_file_ids(&[filename]).unwrap()[0]
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("instadown-storage-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn deletes_oldest_but_spares_the_whole_finished_job() {
+        let dir = temp_dir("delete");
+        let mut library = Library::open(Path::new(":memory:")).unwrap();
+        download(&mut library, &dir, "old.mp4", 100);
+        download(&mut library, &dir, "older_carousel_1.jpg", 200);
+        // A carousel of three just finished
+        let keep: Vec<i64> = ["new_1.jpg", "new_2.jpg", "new_3.jpg"]
+            .iter()
+            .map(|name| download(&mut library, &dir, name, 300))
+            .collect();
+
+        let config = StorageConfig { quota_mb: Some(2), ..StorageConfig::default() };
+        let pruned = enforce_quota(&config, &mut library, &keep).unwrap();
+
+        assert_eq!((pruned.files, pruned.bytes, pruned.moved), (2, 2 * MIB, false));
+        assert!(!dir.join("old.mp4").exists());
+        assert!(!dir.join("older_carousel_1.jpg").exists());
+        for name in ["new_1.jpg", "new_2.jpg", "new_3.jpg"] {
+            assert!(dir.join(name).exists(), "{} was deleted", name);
+        }
+        assert_eq!(library.stored_files().unwrap().len(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn leaves_files_alone_within_the_quota() {
+        let dir = temp_dir("within");
+        let mut library = Library::open(Path::new(":memory:")).unwrap();
+        download(&mut library, &dir, "a.mp4", 100);
+        download(&mut library, &dir, "b.mp4", 200);
+
+        let config = StorageConfig { quota_mb: Some(2), ..StorageConfig::default() };
+        assert_eq!(enforce_quota(&config, &mut library, &[]).unwrap().files, 0);
+        assert_eq!(enforce_quota(&StorageConfig::default(), &mut library, &[]).unwrap().files, 0);
+        assert!(dir.join("a.mp4").exists() && dir.join("b.mp4").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn moves_to_cold_storage_without_overwriting() {
+        let dir = temp_dir("cold");
+        let cold = dir.join("cold");
+        fs::create_dir_all(&cold).unwrap();
+        fs::write(cold.join("clip.mp4"), "already here").unwrap();
+        let mut library = Library::open(Path::new(":memory:")).unwrap();
+        let old = download(&mut library, &dir, "clip.mp4", 100);
+        let new = download(&mut library, &dir, "new.mp4", 200);
+
+        let config = StorageConfig {
+            quota_mb: Some(1),
+            retention: RetentionPolicy::ColdStorage,
+            cold_storage: Some(cold.clone()),
+            ..StorageConfig::default()
+        };
+        let pruned = enforce_quota(&config, &mut library, &[new]).unwrap();
+
+        assert_eq!((pruned.files, pruned.moved), (1, true));
+        assert_eq!(fs::read_to_string(cold.join("clip.mp4")).unwrap(), "already here");
+        let moved = cold.join("clip (2).mp4");
+        assert_eq!(fs::metadata(&moved).unwrap().len(), MIB);
+        assert!(!dir.join("clip.mp4").exists());
+        assert_eq!(library.media_file_ids(&[moved.to_string_lossy().to_string()]).unwrap(), [old]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, TimeZone};
+use std::io::{BufRead, BufReader};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use super::{DaemonInfo, EnqueueRequest, EnqueueResponse, JobList, ServerEvent};
+use crate::core::schedule::TimeWindow;
+use crate::core::Config;
+use crate::downloader::queue::{Job, QueueEvent};
+use crate::ui::app::DownloadStatus;
+
+/// A running daemon's queue, seen through its HTTP API
+pub struct RemoteQueue {
+    base_url: String,
+    auth: String,
+    agent: ureq::Agent,
+    events: Receiver<ServerEvent>,
+    current: Option<Job>,
+    pending: usize,
+    waiting_until: Option<DateTime<Local>>,
+    disconnected: bool,
+}
+
+impl RemoteQueue {
+    pub fn connect(config: &Config) -> Result<Self> {
+        let info = DaemonInfo::read(config)?;
+        let base_url = info.base_url();
+        let auth = format!("Bearer {}", info.token);
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(2))
+            .build();
+
+        // Subscribe before taking the snapshot so no event falls in between
+        let stream = agent
+            .get(&format!("{}/events", base_url))
+            .set("Authorization", &auth)
+            .call()
+            .with_context(|| format!("Could not reach the daemon at {}", base_url))?;
+        let (tx, events) = mpsc::channel();
+        thread::spawn(move || {
+            let reader = BufReader::new(stream.into_reader());
+            for line in reader.lines().map_while(Result::ok) {
+                let Some(data) = line.strip_prefix("data: ") else {
+                    continue;
+                };
+                if let Ok(event) = serde_json::from_str(data) {
+                    if tx.send(event).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        let jobs: JobList = agent
+            .get(&format!("{}/jobs", base_url))
+            .set("Authorization", &auth)
+            .call()?
+            .into_json()?;
+
+        Ok(Self {
+            base_url,
+            auth,
+            agent,
+            events,
+            current: jobs.running,
+            pending: jobs.pending.len(),
+            waiting_until: from_timestamp(jobs.waiting_until),
+            disconnected: false,
+        })
+    }
+
+    /// Returns the new job's id, or `None` if the daemon skipped a duplicate
+    pub fn enqueue(&self, url: &str, force: bool, window: Option<TimeWindow>) -> Result<Option<u64>> {
+        let response: EnqueueResponse = self
+            .agent
+            .post(&format!("{}/jobs", self.base_url))
+            .set("Authorization", &self.auth)
+            .send_json(EnqueueRequest { url: url.to_string(), force, window })?
+            .into_json()?;
+        Ok(response.id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending
+    }
+
+    pub fn current(&self) -> Option<&Job> {
+        self.current.as_ref()
+    }
+
+    pub fn waiting_until(&self) -> Option<DateTime<Local>> {
+        self.waiting_until
+    }
+
+    pub fn cancel_current(&self) -> bool {
+        let Some(job) = &self.current else {
+            return false;
+        };
+        self.agent
+            .delete(&format!("{}/jobs/{}", self.base_url, job.id))
+            .set("Authorization", &self.auth)
+            .call()
+            .is_ok()
+    }
+
+    /// Turns the daemon's events into the same updates a local queue gives
+    pub fn poll(&mut self) -> Vec<QueueEvent> {
+        let mut updates = Vec::new();
+
+        loop {
+            let event = match self.events.try_recv() {
+                Ok(event) => event,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if !self.disconnected {
+                        self.disconnected = true;
+                        self.current = None;
+                        updates.push(QueueEvent::Progress(DownloadStatus::Error(
+                            "Lost connection to the daemon".to_string(),
+                        )));
+                    }
+                    break;
+                }
+            };
+
+            match event {
+                ServerEvent::Queued { pending, .. } | ServerEvent::Removed { pending, .. } => self.pending = pending,
+                ServerEvent::Started { job, pending } => {
+                    self.current = Some(job);
+                    self.pending = pending;
+                    updates.push(QueueEvent::Started);
+                }
+                ServerEvent::Progress { status } => updates.push(QueueEvent::Progress(status)),
+                ServerEvent::Waiting { until } => self.waiting_until = from_timestamp(until),
+                ServerEvent::Finished { job, outcome, duration_ms } => {
+                    self.current = None;
+                    updates.push(QueueEvent::Finished {
+                        job,
+                        outcome,
+                        duration: Duration::from_millis(duration_ms),
+                    });
+                }
+            }
+        }
+
+        updates
+    }
+}
+
+fn from_timestamp(seconds: Option<i64>) -> Option<DateTime<Local>> {
+    seconds.and_then(|seconds| Local.timestamp_opt(seconds, 0).single())
+}
@@ -0,0 +1,126 @@
+pub mod client;
+pub mod server;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+
+use crate::core::schedule::TimeWindow;
+use crate::core::Config;
+use crate::downloader::queue::{Job, JobOutcome};
+use crate::ui::app::DownloadStatus;
+
+/// How clients find a running daemon. Written to the data directory,
+/// readable only by the current user since it holds the API token.
+#[derive(Serialize, Deserialize)]
+pub struct DaemonInfo {
+    pub port: u16,
+    pub token: String,
+    pub pid: u32,
+}
+
+impl DaemonInfo {
+    pub fn read(config: &Config) -> Result<Self> {
+        let path = config.daemon_info_path();
+        let json = fs::read_to_string(&path)
+            .with_context(|| format!("No running daemon found ({} is missing)", path.display()))?;
+        serde_json::from_str(&json).context("Daemon info file is corrupt")
+    }
+
+    pub fn write(&self, config: &Config) -> Result<()> {
+        let path = config.daemon_info_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options
+            .open(&path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        // The mode above only applies when the file is created
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+                .with_context(|| format!("Failed to restrict {}", path.display()))?;
+        }
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.port)
+    }
+}
+
+/// Messages on the daemon's `/events` stream
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ServerEvent {
+    Queued { job: Job, pending: usize },
+    Started { job: Job, pending: usize },
+    Progress { status: DownloadStatus },
+    Finished { job: Job, outcome: JobOutcome, duration_ms: u64 },
+    Removed { id: u64, pending: usize },
+    /// Nothing can start before `until` (Unix seconds) because of quiet
+    /// hours or job windows; `None` once that no longer holds
+    Waiting { until: Option<i64> },
+}
+
+impl ServerEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Queued { .. } => "queued",
+            Self::Started { .. } => "started",
+            Self::Progress { .. } => "progress",
+            Self::Finished { .. } => "finished",
+            Self::Removed { .. } => "removed",
+            Self::Waiting { .. } => "waiting",
+        }
+    }
+}
+
+/// Body of `POST /jobs`
+#[derive(Serialize, Deserialize)]
+pub struct EnqueueRequest {
+    pub url: String,
+    /// Download again even if the library already has the file
+    #[serde(default)]
+    pub force: bool,
+    /// Only start the job within this daily window, e.g. `01:00-06:00`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<TimeWindow>,
+}
+
+/// Reply to `POST /jobs`; `id` is missing when the URL was skipped
+#[derive(Serialize, Deserialize)]
+pub struct EnqueueResponse {
+    pub id: Option<u64>,
+    pub skipped: bool,
+}
+
+/// Reply to `GET /jobs`
+#[derive(Serialize, Deserialize)]
+pub struct JobList {
+    pub running: Option<Job>,
+    pub progress: Option<DownloadStatus>,
+    pub pending: Vec<Job>,
+    /// See `ServerEvent::Waiting`
+    #[serde(default)]
+    pub waiting_until: Option<i64>,
+}
+
+/// 256 bits from the OS, hex encoded
+pub fn generate_token() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| anyhow::anyhow!("Failed to generate API token: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
@@ -0,0 +1,464 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::{Cursor, Write};
+use std::process;
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+
+use super::{generate_token, DaemonInfo, EnqueueRequest, EnqueueResponse, JobList, ServerEvent};
+use crate::core::disk::format_size;
+use crate::core::history::{DateRange, HistoryFilter, StatusFilter};
+use crate::core::schedule::TimeWindow;
+use crate::core::{shutdown, storage, Config};
+use crate::downloader::queue::{DownloadQueue, Job, JobOutcome, QueueEvent};
+use crate::downloader::InstagramDownloader;
+use crate::gallery::{Gallery, GalleryUpdater};
+use crate::ipc::{self, InstanceListener};
+use crate::library::Library;
+use crate::subscriptions::{SubscriptionEvent, SubscriptionPoller};
+use crate::ui::app::{Download, DownloadStatus};
+
+/// How many finished jobs `GET /jobs/{id}` can still report on
+const FINISHED_JOBS_KEPT: usize = 200;
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+/// Events an `/events` client may fall behind by before it is dropped
+const SUBSCRIBER_BACKLOG: usize = 64;
+
+/// The download queue running headless, driven over HTTP
+struct Daemon {
+    token: String,
+    library: Library,
+    queue: DownloadQueue,
+    gallery: GalleryUpdater,
+    progress: Option<DownloadStatus>,
+    finished: VecDeque<(Job, JobOutcome)>,
+    /// One writer thread per `/events` client, so a slow one can't stall the queue
+    subscribers: Vec<SyncSender<Arc<str>>>,
+    listener: Option<InstanceListener>,
+    subscriptions: Option<SubscriptionPoller>,
+    /// Last `ServerEvent::Waiting` sent, so it only goes out on changes
+    waiting_until: Option<i64>,
+    /// Whether the queue was last seen paused for disk space
+    low_on_space: bool,
+}
+
+/// State of one job as reported by `GET /jobs/{id}`
+#[derive(Serialize)]
+struct JobStatus<'a> {
+    #[serde(flatten)]
+    job: &'a Job,
+    state: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    progress: Option<&'a DownloadStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outcome: Option<&'a JobOutcome>,
+}
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+/// Serves the API on localhost until interrupted or terminated
+pub fn run(config: Config, port: u16) -> Result<()> {
+    shutdown::install();
+    let mut library = Library::open(&config.library_path())?;
+    let mut queue = DownloadQueue::new(InstagramDownloader::new(config.clone())?);
+    let restored = queue.restore(config.queue_path(), &mut library)?;
+
+    let http = Server::http(("127.0.0.1", port)).map_err(|e| anyhow!("Failed to listen on port {}: {}", port, e))?;
+    let port = http
+        .server_addr()
+        .to_ip()
+        .map(|addr| addr.port())
+        .ok_or_else(|| anyhow!("Server is not listening on a TCP port"))?;
+
+    let info = DaemonInfo {
+        port,
+        token: generate_token()?,
+        pid: process::id(),
+    };
+    info.write(&config)?;
+    println!("Listening on {}", info.base_url());
+    println!("API token stored in {}", config.daemon_info_path().display());
+    println!("Using yt-dlp {}", queue.downloader().version());
+    if let Some(warning) = config.settings.tls.warning() {
+        eprintln!("Warning: {}", warning);
+    }
+    if let Some(limits) = config.settings.rate_limit.summary() {
+        println!("Rate limits: {}", limits);
+    }
+    println!("Storage: {}", config.settings.storage.summary());
+    if let Some(summary) = restored.summary() {
+        println!("{}", summary);
+    }
+
+    let subscriptions = SubscriptionPoller::from_config(&config, queue.shared_downloader());
+    let mut daemon = Daemon {
+        token: info.token,
+        library,
+        queue,
+        gallery: GalleryUpdater::new(Gallery::new(config.gallery_dir())),
+        progress: None,
+        finished: VecDeque::new(),
+        subscribers: Vec::new(),
+        listener: ipc::listen(&config)?,
+        subscriptions,
+        waiting_until: None,
+        low_on_space: false,
+    };
+
+    while !shutdown::requested() {
+        if let Some(request) = http.recv_timeout(Duration::from_millis(100))? {
+            daemon.handle(request);
+        }
+        daemon.tick();
+    }
+
+    println!("Shutting down");
+    // The queue records its running job as released when dropped
+    drop(daemon);
+    let _ = std::fs::remove_file(config.daemon_info_path());
+    Ok(())
+}
+
+impl Daemon {
+    fn handle(&mut self, mut request: Request) {
+        if !self.authorized(&request) {
+            let _ = request.respond(error(401, "Missing or wrong API token"));
+            return;
+        }
+
+        let (path, query) = split_url(request.url());
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+        let response = match (request.method(), segments.as_slice()) {
+            (Method::Get, ["events"]) => {
+                self.subscribe(request);
+                return;
+            }
+            (Method::Get, ["jobs"]) => json(200, &JobList {
+                running: self.queue.current().cloned(),
+                progress: self.progress.clone(),
+                pending: self.queue.pending().cloned().collect(),
+                waiting_until: self.waiting_until,
+            }),
+            (Method::Post, ["jobs"]) => {
+                let mut body = String::new();
+                match request
+                    .as_reader()
+                    .read_to_string(&mut body)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|_| Ok(serde_json::from_str::<EnqueueRequest>(&body)?))
+                {
+                    Ok(body) => self.enqueue(body),
+                    Err(e) => error(400, &format!("Expected {{\"url\": ...}}: {}", e)),
+                }
+            }
+            (Method::Get, ["jobs", id]) => match id.parse() {
+                Ok(id) => self.job_status(id),
+                Err(_) => error(400, "Job ids are numbers"),
+            },
+            (Method::Delete, ["jobs", id]) => match id.parse() {
+                Ok(id) => self.cancel(id),
+                Err(_) => error(400, "Job ids are numbers"),
+            },
+            (Method::Get, ["history"]) => self.history(&query),
+            _ => error(404, "No such endpoint"),
+        };
+
+        let _ = request.respond(response);
+    }
+
+    /// Accepts the token as a bearer header, or as `?token=` for
+    /// clients like the browser's EventSource that can't set headers
+    fn authorized(&self, request: &Request) -> bool {
+        let header = request
+            .headers()
+            .iter()
+            .find(|h| h.field.equiv("Authorization"))
+            .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+            .map(str::to_string);
+        let query = split_url(request.url())
+            .1
+            .into_iter()
+            .find(|(key, _)| key == "token")
+            .map(|(_, value)| value);
+
+        header
+            .or(query)
+            .is_some_and(|token| constant_time_eq(token.as_bytes(), self.token.as_bytes()))
+    }
+
+    fn enqueue(&mut self, body: EnqueueRequest) -> HttpResponse {
+        match self.enqueue_url(body.url, body.force, body.window) {
+            Ok(Some(id)) => json(202, &EnqueueResponse { id: Some(id), skipped: false }),
+            Ok(None) => json(200, &EnqueueResponse { id: None, skipped: true }),
+            Err(e) => error(500, &e.to_string()),
+        }
+    }
+
+    fn enqueue_url(&mut self, url: String, force: bool, window: Option<TimeWindow>) -> Result<Option<u64>> {
+        let id = self.queue.enqueue_unless_downloaded(&mut self.library, url.clone(), force, window)?;
+        if let Some(id) = id {
+            self.broadcast(&ServerEvent::Queued {
+                job: Job { id, url, window, user_agent: None },
+                pending: self.queue.len(),
+            });
+        }
+        Ok(id)
+    }
+
+    fn job_status(&self, id: u64) -> HttpResponse {
+        if let Some(job) = self.queue.current().filter(|job| job.id == id) {
+            return json(200, &JobStatus { job, state: "running", progress: self.progress.as_ref(), outcome: None });
+        }
+        if let Some(job) = self.queue.pending().find(|job| job.id == id) {
+            return json(200, &JobStatus { job, state: "pending", progress: None, outcome: None });
+        }
+        match self.finished.iter().find(|(job, _)| job.id == id) {
+            Some((job, outcome)) => {
+                json(200, &JobStatus { job, state: outcome.label(), progress: None, outcome: Some(outcome) })
+            }
+            None => error(404, "No such job"),
+        }
+    }
+
+    fn cancel(&mut self, id: u64) -> HttpResponse {
+        if self.queue.current().is_some_and(|job| job.id == id) {
+            self.queue.cancel_current();
+        } else if self.queue.remove(id) {
+            self.broadcast(&ServerEvent::Removed { id, pending: self.queue.len() });
+        } else {
+            return error(404, "No such job");
+        }
+        Response::from_data(Vec::new()).with_status_code(204)
+    }
+
+    /// `GET /history?q=&status=&profile=&days=&limit=`, newest first
+    fn history(&self, query: &[(String, String)]) -> HttpResponse {
+        let param = |name: &str| query.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+
+        let mut filter = HistoryFilter {
+            query: param("q").unwrap_or_default().to_string(),
+            profile: param("profile").map(str::to_string),
+            ..HistoryFilter::default()
+        };
+        if let Some(status) = param("status") {
+            match std::iter::successors(Some(StatusFilter::All), |s| Some(s.next()))
+                .take(5)
+                .find(|s| s.label() == status)
+            {
+                Some(status) => filter.status = status,
+                None => return error(400, "status must be all, completed, failed, cancelled or skipped"),
+            }
+        }
+        if let Some(days) = param("days") {
+            let since = days
+                .parse::<i64>()
+                .ok()
+                .filter(|days| *days >= 0)
+                .and_then(chrono::TimeDelta::try_days)
+                .and_then(|days| chrono::Local::now().checked_sub_signed(days));
+            match since {
+                Some(since) => filter.date = DateRange::Between(Some(since), None),
+                None => return error(400, "days must be a whole number, 0 or more, within the supported date range"),
+            }
+        }
+        let limit = param("limit").and_then(|l| l.parse().ok()).unwrap_or(DEFAULT_HISTORY_LIMIT);
+
+        match self.library.history_window(&filter, 0, Some(limit)) {
+            Ok(downloads) => json(200, &downloads),
+            Err(e) => error(500, &e.to_string()),
+        }
+    }
+
+    /// Keeps the connection open as a server-sent event stream
+    fn subscribe(&mut self, request: Request) {
+        let mut writer = request.into_writer();
+        let head = "HTTP/1.1 200 OK\r\n\
+                    Content-Type: text/event-stream\r\n\
+                    Cache-Control: no-cache\r\n\
+                    Connection: close\r\n\r\n";
+        if writer.write_all(head.as_bytes()).and_then(|_| writer.flush()).is_err() {
+            return;
+        }
+
+        let (tx, rx) = mpsc::sync_channel::<Arc<str>>(SUBSCRIBER_BACKLOG);
+        thread::spawn(move || {
+            // Ends when the client goes away or the daemon drops the sender
+            for message in rx {
+                if writer.write_all(message.as_bytes()).and_then(|_| writer.flush()).is_err() {
+                    break;
+                }
+            }
+        });
+        self.subscribers.push(tx);
+    }
+
+    fn broadcast(&mut self, event: &ServerEvent) {
+        let Ok(data) = serde_json::to_string(event) else {
+            return;
+        };
+        let message: Arc<str> = format!("event: {}\ndata: {}\n\n", event.name(), data).into();
+
+        // Clients that went away, or fell too far behind, are dropped
+        self.subscribers.retain(|tx| tx.try_send(Arc::clone(&message)).is_ok());
+    }
+
+    fn tick(&mut self) {
+        let forwarded = self.listener.as_ref().map(|l| l.poll()).unwrap_or_default();
+        for request in forwarded {
+            let queued = self.enqueue_url(request.url.clone(), request.force, request.window);
+            if let Err(e) = &queued {
+                eprintln!("Failed to queue {}: {}", request.url, e);
+            }
+            request.respond(matches!(queued, Ok(Some(_))));
+        }
+
+        if let Some(Err(e)) = self.gallery.poll() {
+            eprintln!("Failed to update gallery: {}", e);
+        }
+
+        let checked = match &mut self.subscriptions {
+            Some(poller) => poller.tick(&mut self.library),
+            None => Ok(Vec::new()),
+        };
+        match checked {
+            Ok(events) => {
+                for event in events {
+                    match event {
+                        SubscriptionEvent::Found { label, urls } => {
+                            if !urls.is_empty() {
+                                println!("{}: {} new post{}", label, urls.len(), if urls.len() == 1 { "" } else { "s" });
+                            }
+                            for url in urls {
+                                if let Err(e) = self.enqueue_url(url.clone(), false, None) {
+                                    eprintln!("Failed to queue {}: {}", url, e);
+                                }
+                            }
+                        }
+                        SubscriptionEvent::Failed { label, message } => eprintln!("Failed to check {}: {}", label, message),
+                    }
+                }
+            }
+            Err(e) => eprintln!("Failed to check subscriptions: {}", e),
+        }
+
+        for event in self.queue.poll() {
+            match event {
+                QueueEvent::Started => {
+                    self.progress = Some(DownloadStatus::InProgress);
+                    if let Some(job) = self.queue.current().cloned() {
+                        println!("Started #{}: {} ({})", job.id, job.url, job.user_agent.as_deref().unwrap_or_default());
+                        self.broadcast(&ServerEvent::Started { job, pending: self.queue.len() });
+                    }
+                }
+                QueueEvent::Progress(status) => {
+                    self.broadcast(&ServerEvent::Progress { status: status.clone() });
+                    self.progress = Some(status);
+                }
+                QueueEvent::HookFailed(message) => eprintln!("{}", message),
+                QueueEvent::Retrying { job, message } => {
+                    self.progress = None;
+                    eprintln!("{} during #{}, retrying", message, job.id);
+                    self.broadcast(&ServerEvent::Queued { job, pending: self.queue.len() });
+                }
+                QueueEvent::Finished { job, outcome, duration } => {
+                    self.progress = None;
+                    let downloads = Download::from_outcome(&job, &outcome, duration);
+                    if let Some(download) = downloads.first() {
+                        println!("Finished #{}: {}", job.id, download.status);
+                    }
+                    outcome.warnings().iter().for_each(|warning| eprintln!("{}", warning));
+                    for download in &downloads {
+                        if let Err(e) = self.library.record_attempt(download) {
+                            eprintln!("Failed to save history: {}", e);
+                        }
+                    }
+                    if let JobOutcome::Completed { files } = &outcome {
+                        let storage = &self.queue.downloader().config().settings.storage;
+                        let paths: Vec<String> = files.iter().map(|info| info.filepath.clone()).collect();
+                        let pruned = self
+                            .library
+                            .media_file_ids(&paths)
+                            .and_then(|keep| storage::enforce_quota(storage, &mut self.library, &keep));
+                        match pruned {
+                            Ok(pruned) => pruned.summary().into_iter().for_each(|summary| println!("{}", summary)),
+                            Err(e) => eprintln!("Storage quota: {:#}", e),
+                        }
+                        let config = self.queue.downloader().config();
+                        match self.library.gallery_posts(&config.output_dir, &[&config.gallery_dir()]) {
+                            Ok(posts) => self.gallery.schedule(posts),
+                            Err(e) => eprintln!("Failed to update gallery: {}", e),
+                        }
+                    }
+
+                    self.broadcast(&ServerEvent::Finished {
+                        job: job.clone(),
+                        outcome: outcome.clone(),
+                        duration_ms: duration.as_millis() as u64,
+                    });
+                    self.finished.push_front((job, outcome));
+                    self.finished.truncate(FINISHED_JOBS_KEPT);
+                }
+            }
+        }
+
+        let low_on_space = self.queue.low_on_space();
+        if low_on_space.is_some() != self.low_on_space {
+            self.low_on_space = low_on_space.is_some();
+            match low_on_space {
+                Some((free, needed)) => println!(
+                    "Queue paused: {} free, waiting for {}",
+                    format_size(free),
+                    format_size(needed)
+                ),
+                None => println!("Enough disk space again, resuming"),
+            }
+        }
+
+        let waiting_until = self.queue.waiting_until().map(|until| until.timestamp());
+        if waiting_until != self.waiting_until {
+            self.waiting_until = waiting_until;
+            if let Some(until) = self.queue.waiting_until() {
+                println!("Queue paused until {}", until.format("%Y-%m-%d %H:%M"));
+            }
+            self.broadcast(&ServerEvent::Waiting { until: waiting_until });
+        }
+    }
+}
+
+fn json(status: u16, body: &impl Serialize) -> HttpResponse {
+    let body = serde_json::to_vec(body).unwrap_or_default();
+    Response::from_data(body)
+        .with_status_code(StatusCode(status))
+        .with_header(Header::from_bytes("Content-Type", "application/json").unwrap())
+}
+
+fn error(status: u16, message: &str) -> HttpResponse {
+    json(status, &serde_json::json!({ "error": message }))
+}
+
+fn split_url(url: &str) -> (&str, Vec<(String, String)>) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let decode = |s: &str| {
+                urlencoding::decode(&s.replace('+', " "))
+                    .map(|s| s.into_owned())
+                    .unwrap_or_default()
+            };
+            (decode(key), decode(value))
+        })
+        .collect();
+    (path, params)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
@@ -0,0 +1,203 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+/// Oldest yt-dlp whose Instagram extractor and options instadown relies on
+pub const MIN_VERSION: YtDlpVersion = YtDlpVersion { year: 2023, month: 7, day: 6, build: 0 };
+
+/// The `[yt_dlp]` section of `config.toml`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct YtDlpConfig {
+    /// The yt-dlp executable, instead of the one on PATH
+    pub path: Option<PathBuf>,
+    /// Run `<python> -m yt_dlp` instead of an executable
+    pub python: Option<String>,
+    /// Accept versions from this one on instead of `MIN_VERSION`
+    pub min_version: Option<YtDlpVersion>,
+}
+
+impl YtDlpConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.path.is_some() && self.python.is_some() {
+            bail!("Set either `path` or `python` for yt-dlp, not both");
+        }
+        Ok(())
+    }
+}
+
+/// A yt-dlp release such as `2024.08.06`, or a nightly `2024.08.06.232758`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "String")]
+pub struct YtDlpVersion {
+    year: u32,
+    month: u32,
+    day: u32,
+    build: u64,
+}
+
+impl YtDlpVersion {
+    /// The day the release was made
+    pub fn date(&self) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(self.year as i32, self.month, self.day)
+    }
+}
+
+impl FromStr for YtDlpVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let token = s.split_whitespace().next().unwrap_or_default();
+        let parts: Vec<&str> = token.split('.').collect();
+        let invalid = || anyhow!("{:?} is not a yt-dlp version", s.trim());
+        let number = |index: usize| parts[index].parse::<u32>().map_err(|_| invalid());
+        if !(3..=4).contains(&parts.len()) {
+            return Err(invalid());
+        }
+        let version = Self {
+            year: number(0)?,
+            month: number(1)?,
+            day: number(2)?,
+            build: if parts.len() == 4 { parts[3].parse().map_err(|_| invalid())? } else { 0 },
+        };
+        // Also rules out months and days that don't exist
+        version.date().ok_or_else(invalid)?;
+        Ok(version)
+    }
+}
+
+impl TryFrom<String> for YtDlpVersion {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for YtDlpVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02}.{:02}", self.year, self.month, self.day)?;
+        if self.build > 0 {
+            write!(f, ".{}", self.build)?;
+        }
+        Ok(())
+    }
+}
+
+/// How yt-dlp is started: an executable, or a Python module
+#[derive(Debug, Clone)]
+pub struct YtDlp {
+    program: String,
+    args: Vec<String>,
+    min_version: YtDlpVersion,
+}
+
+impl YtDlp {
+    pub fn new(config: &YtDlpConfig) -> Self {
+        let (program, args) = match (&config.python, &config.path) {
+            (Some(python), _) => (python.clone(), vec!["-m".to_string(), "yt_dlp".to_string()]),
+            (None, Some(path)) => (path.to_string_lossy().to_string(), Vec::new()),
+            (None, None) => ("yt-dlp".to_string(), Vec::new()),
+        };
+        Self { program, args, min_version: config.min_version.unwrap_or(MIN_VERSION) }
+    }
+
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        command
+    }
+
+    /// "yt-dlp" or "python3 -m yt_dlp"
+    pub fn invocation(&self) -> String {
+        std::iter::once(&self.program).chain(&self.args).cloned().collect::<Vec<_>>().join(" ")
+    }
+
+    pub fn min_version(&self) -> YtDlpVersion {
+        self.min_version
+    }
+
+    /// Runs `--version` and parses the answer
+    pub fn version(&self) -> Result<YtDlpVersion> {
+        let output = self
+            .command()
+            .arg("--version")
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => anyhow!(
+                    "{} is not installed or not in PATH. Please install yt-dlp first, or set `path` or `python` under [yt_dlp].",
+                    self.program
+                ),
+                _ => anyhow!("Failed to start {}: {}", self.invocation(), e),
+            })?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("`{} --version` failed: {}", self.invocation(), stderr.lines().last().unwrap_or_default().trim());
+        }
+        String::from_utf8_lossy(&output.stdout).trim().parse()
+    }
+
+    /// The version, provided it is one instadown works with
+    pub fn check(&self) -> Result<YtDlpVersion> {
+        let version = self.version()?;
+        if version < self.min_version {
+            bail!(
+                "yt-dlp {} is older than {}, the oldest known to work. Update it (`yt-dlp -U` or your package manager), or lower `min_version` under [yt_dlp] at your own risk.",
+                version,
+                self.min_version
+            );
+        }
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(s: &str) -> YtDlpVersion {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_releases_and_nightlies() {
+        assert_eq!(version("2024.08.06"), YtDlpVersion { year: 2024, month: 8, day: 6, build: 0 });
+        assert_eq!(version("2024.08.06.232758\n"), YtDlpVersion { year: 2024, month: 8, day: 6, build: 232758 });
+        // pip normalises away the leading zeros
+        assert_eq!(version("2024.8.6"), version("2024.08.06"));
+        assert_eq!(version("2023.07.06 (nightly)").date(), NaiveDate::from_ymd_opt(2023, 7, 6));
+    }
+
+    #[test]
+    fn rejects_anything_else() {
+        for bad in ["", "yt-dlp", "2024.08", "2024.08.06.1.2", "2024.08.xx", "2024.13.01", "2024.02.30", "-1.08.06"] {
+            assert!(bad.parse::<YtDlpVersion>().is_err(), "{:?} parsed", bad);
+        }
+    }
+
+    #[test]
+    fn orders_by_date_then_build() {
+        assert!(version("2023.07.06") < version("2023.07.06.1"));
+        assert!(version("2023.07.06.999999") < version("2023.07.07"));
+        assert!(version("2023.12.31") < version("2024.01.01"));
+        assert!(MIN_VERSION <= version("2023.07.06"));
+    }
+
+    #[test]
+    fn displays_like_yt_dlp() {
+        assert_eq!(version("2024.8.6").to_string(), "2024.08.06");
+        assert_eq!(version("2024.08.06.232758").to_string(), "2024.08.06.232758");
+    }
+
+    #[test]
+    fn reads_min_version_from_config() {
+        let config: YtDlpConfig = toml::from_str("min_version = \"2022.10.04\"").unwrap();
+        assert_eq!(config.min_version, Some(version("2022.10.04")));
+        assert!(toml::from_str::<YtDlpConfig>("min_version = \"latest\"").is_err());
+    }
+}
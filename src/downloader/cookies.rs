@@ -0,0 +1,143 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Only cookies for these sites are ever written to the jar
+const ALLOWED_DOMAINS: &[&str] = &["instagram.com"];
+
+/// A cookie as the WebExtensions `cookies` API describes it
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowserCookie {
+    pub domain: String,
+    pub name: String,
+    pub value: String,
+    #[serde(default = "root_path")]
+    pub path: String,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default)]
+    pub http_only: bool,
+    #[serde(default)]
+    pub host_only: bool,
+    /// Seconds since the epoch; missing for session cookies
+    pub expiration_date: Option<f64>,
+}
+
+fn root_path() -> String {
+    "/".to_string()
+}
+
+impl BrowserCookie {
+    fn allowed(&self) -> bool {
+        let domain = self.domain.trim_start_matches('.');
+        ALLOWED_DOMAINS
+            .iter()
+            .any(|allowed| domain == *allowed || domain.ends_with(&format!(".{}", allowed)))
+    }
+
+    /// One line of a Netscape cookies.txt, the format yt-dlp's `--cookies` reads
+    fn to_line(&self) -> String {
+        let domain = if self.host_only || self.domain.starts_with('.') {
+            self.domain.clone()
+        } else {
+            format!(".{}", self.domain)
+        };
+        format!(
+            "{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            if self.http_only { "#HttpOnly_" } else { "" },
+            domain,
+            if domain.starts_with('.') { "TRUE" } else { "FALSE" },
+            self.path,
+            if self.secure { "TRUE" } else { "FALSE" },
+            self.expiration_date.map(|e| e as u64).unwrap_or(0),
+            self.name,
+            self.value,
+        )
+    }
+}
+
+/// Adds `cookies` to the jar at `path`, replacing older values of the same
+/// cookie. Returns how many were taken; cookies for other sites are ignored.
+pub fn merge_into(path: &Path, cookies: &[BrowserCookie]) -> Result<usize> {
+    // Keyed by (domain, path, name) so a fresh login replaces the old session
+    let mut jar: BTreeMap<(String, String, String), String> = BTreeMap::new();
+    if let Ok(existing) = fs::read_to_string(path) {
+        for line in existing.lines() {
+            if let Some(key) = cookie_key(line) {
+                jar.insert(key, line.to_string());
+            }
+        }
+    }
+
+    let mut taken = 0;
+    for cookie in cookies.iter().filter(|cookie| cookie.allowed()) {
+        let line = cookie.to_line();
+        if let Some(key) = cookie_key(&line) {
+            jar.insert(key, line);
+            taken += 1;
+        }
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to write cookies to {}", path.display()))?;
+    writeln!(file, "# Netscape HTTP Cookie File")?;
+    for line in jar.values() {
+        writeln!(file, "{}", line)?;
+    }
+
+    Ok(taken)
+}
+
+/// What the jar holds, without any cookie values
+pub struct JarSummary {
+    pub count: usize,
+    /// Expiry of Instagram's `sessionid` login cookie in seconds since the
+    /// epoch, 0 for one that lasts the browser session; `None` without one
+    pub session_expires: Option<u64>,
+}
+
+pub fn summarize(path: &Path) -> Result<JarSummary> {
+    let jar = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut summary = JarSummary { count: 0, session_expires: None };
+    for line in jar.lines() {
+        let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+        let fields: Vec<&str> = line.split('\t').collect();
+        if line.starts_with('#') || fields.len() != 7 {
+            continue;
+        }
+        summary.count += 1;
+        if fields[5] == "sessionid" {
+            summary.session_expires = fields[4].parse().ok();
+        }
+    }
+    Ok(summary)
+}
+
+fn cookie_key(line: &str) -> Option<(String, String, String)> {
+    let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+    if line.starts_with('#') || line.trim().is_empty() {
+        return None;
+    }
+
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() != 7 {
+        return None;
+    }
+    Some((fields[0].to_string(), fields[2].to_string(), fields[5].to_string()))
+}
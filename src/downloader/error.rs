@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Marker error returned when a download was stopped by the user
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Download cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Marker error for yt-dlp being killed by a signal it didn't get from us,
+/// such as a segfault or the OOM killer
+#[derive(Debug)]
+pub struct Crashed(pub String);
+
+impl fmt::Display for Crashed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "yt-dlp crashed: {}", self.0)
+    }
+}
+
+impl std::error::Error for Crashed {}
+
+/// Marker error for a download queue journal another process holds
+#[derive(Debug)]
+pub struct QueueInUse;
+
+impl fmt::Display for QueueInUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Another instadown is already running the download queue")
+    }
+}
+
+impl std::error::Error for QueueInUse {}
+
+/// Rough category of a failed download, derived from yt-dlp's error output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    Network,
+    RateLimited,
+    LoginRequired,
+    Private,
+    NotFound,
+    Unsupported,
+    /// yt-dlp died during the job too many times to try again
+    Crashed,
+    /// The file stayed truncated or unreadable after re-downloading
+    Corrupt,
+    Other,
+}
+
+impl ErrorClass {
+    pub fn classify(message: &str) -> Self {
+        let message = message.to_lowercase();
+        let has = |needles: &[&str]| needles.iter().any(|n| message.contains(n));
+
+        if has(&["429", "too many requests", "rate-limit", "rate limit"]) {
+            Self::RateLimited
+        } else if has(&["login required", "log in", "--cookies", "authentication"]) {
+            Self::LoginRequired
+        } else if has(&["private"]) {
+            Self::Private
+        } else if has(&["404", "not found", "does not exist", "unavailable"]) {
+            Self::NotFound
+        } else if has(&["unsupported url", "no video formats"]) {
+            Self::Unsupported
+        } else if has(&["timed out", "connection", "network", "name or service not known", "unable to download", "ssl"]) {
+            Self::Network
+        } else {
+            Self::Other
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Network => "network",
+            Self::RateLimited => "rate limited",
+            Self::LoginRequired => "login required",
+            Self::Private => "private",
+            Self::NotFound => "not found",
+            Self::Unsupported => "unsupported",
+            Self::Crashed => "crashed",
+            Self::Corrupt => "corrupt",
+            Self::Other => "error",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_yt_dlp_errors() {
+        let cases = [
+            (
+                "ERROR: [Instagram] C5qWk3ZLxyz: Requested content is not available, rate-limit reached or login required. Use --cookies, --cookies-from-browser, --username and --password, --netrc-cmd, or --netrc (instagram) to provide account credentials",
+                ErrorClass::RateLimited,
+            ),
+            (
+                "ERROR: [Instagram] C5qWk3ZLxyz: Unable to download webpage: HTTP Error 429: Too Many Requests (caused by <HTTPError 429: Too Many Requests>)",
+                ErrorClass::RateLimited,
+            ),
+            (
+                "ERROR: [Instagram] C5qWk3ZLxyz: Main webpage is locked behind the login page. Please use --cookies-from-browser or --cookies for the authentication.",
+                ErrorClass::LoginRequired,
+            ),
+            ("ERROR: [Instagram] C5qWk3ZLxyz: This video is private", ErrorClass::Private),
+            (
+                "ERROR: [Instagram] C5qWk3ZLxyz: Unable to download webpage: HTTP Error 404: Not Found (caused by <HTTPError 404: Not Found>)",
+                ErrorClass::NotFound,
+            ),
+            ("ERROR: Unsupported URL: https://www.instagram.com/accounts/login/", ErrorClass::Unsupported),
+            ("ERROR: [Instagram] C5qWk3ZLxyz: No video formats found!", ErrorClass::Unsupported),
+            (
+                "ERROR: [Instagram] C5qWk3ZLxyz: Unable to download webpage: <urlopen error [Errno -3] Temporary failure in name resolution> (caused by TransportError('<urlopen error [Errno -3] Temporary failure in name resolution>'))",
+                ErrorClass::Network,
+            ),
+            (
+                "ERROR: [Instagram] C5qWk3ZLxyz: Unable to download webpage: [SSL: CERTIFICATE_VERIFY_FAILED] certificate verify failed: self-signed certificate in certificate chain (_ssl.c:1000)",
+                ErrorClass::Network,
+            ),
+            ("ERROR: Postprocessing: Conversion failed!", ErrorClass::Other),
+            ("", ErrorClass::Other),
+        ];
+        for (message, class) in cases {
+            assert_eq!(ErrorClass::classify(message), class, "{}", message);
+        }
+    }
+}
@@ -1,13 +1,42 @@
-use anyhow::{Result, Context};
+use anyhow::{bail, Result, Context};
+use serde::{Deserialize, Serialize};
 use std::process::{Command, Stdio};
+use std::sync::atomic::AtomicBool;
 use std::fs;
+use std::path::PathBuf;
 
 use crate::ui::app::DownloadStatus;
 use crate::core::Config;
+use super::backend::{YtDlp, YtDlpVersion};
+use super::error::{Cancelled, ErrorClass};
+use super::proxy::ProxyPool;
+use super::user_agent::UserAgents;
 use super::utils::DownloadUtils;
+use super::verify::{self, VerificationFailed};
+
+/// Metadata yt-dlp prints once the final file has been moved into place
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MediaInfo {
+    pub filepath: String,
+    pub uploader: Option<String>,
+    pub description: Option<String>,
+    /// Hex SHA-256 of the file, once verified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// What verification found odd about a file it kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+    /// Total bytes yt-dlp reported for each stream it downloaded
+    #[serde(default, skip_serializing)]
+    pub stream_sizes: Vec<u64>,
+}
 
 pub struct InstagramDownloader {
     config: Config,
+    backend: YtDlp,
+    version: YtDlpVersion,
+    proxies: ProxyPool,
+    user_agents: UserAgents,
 }
 
 impl InstagramDownloader {
@@ -16,33 +45,183 @@ impl InstagramDownloader {
         fs::create_dir_all(&config.output_dir)
             .context("Failed to create output directory")?;
         
-        // Check if yt-dlp is available
-        DownloadUtils::check_yt_dlp()?;
+        // Refuse to start with a missing or too old yt-dlp
+        let backend = YtDlp::new(&config.settings.yt_dlp);
+        let version = backend.check()?;
+
+        let proxies = ProxyPool::new(&config.settings.proxy);
+        let user_agents = UserAgents::new(&config.settings.user_agent);
+        Ok(Self { config, backend, version, proxies, user_agents })
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The yt-dlp version found at startup
+    pub fn version(&self) -> YtDlpVersion {
+        self.version
+    }
+
+    pub fn proxies(&self) -> &ProxyPool {
+        &self.proxies
+    }
+
+    /// The user agent for the next job, in turn from the configured pool
+    pub fn next_user_agent(&self) -> String {
+        self.user_agents.next()
+    }
 
-        Ok(Self { config })
+    /// Downloads `url`, verifies every file of the post, and moves them into
+    /// the output directory. A post with a file that fails verification is
+    /// downloaded again up to `redownloads` times.
+    pub fn download(
+        &self,
+        url: &str,
+        user_agent: &str,
+        cancel: &AtomicBool,
+        mut status_callback: impl FnMut(DownloadStatus),
+    ) -> Result<Vec<MediaInfo>> {
+        let config = &self.config.settings.verify;
+        let mut redownloads = 0;
+        loop {
+            let mut entries = self.fetch(url, user_agent, cancel, &mut status_callback)?;
+            let verified = entries.iter_mut().try_for_each(|info| {
+                let staged = PathBuf::from(&info.filepath);
+                let name = staged.file_name().unwrap_or_default().to_string_lossy().to_string();
+                let verified = verify::verify(config, &staged, &info.stream_sizes).context(name)?;
+                info.sha256 = Some(verified.sha256);
+                info.warning = verified.warning;
+                anyhow::Ok(())
+            });
+            match verified {
+                Ok(()) => return entries.into_iter().map(|info| self.publish(info)).collect(),
+                Err(e) => {
+                    // Left in place, yt-dlp would take them for finished downloads
+                    for info in &entries {
+                        let _ = fs::remove_file(&info.filepath);
+                    }
+                    if redownloads >= config.redownloads {
+                        return Err(VerificationFailed(format!("{:#}", e)).into());
+                    }
+                    redownloads += 1;
+                }
+            }
+        }
     }
 
-    pub fn download(&self, url: &str, status_callback: impl FnMut(DownloadStatus)) -> Result<String> {
-        let output_template = self.config.output_dir
+    /// One run of yt-dlp into the staging directory, reporting each entry
+    fn fetch(
+        &self,
+        url: &str,
+        user_agent: &str,
+        cancel: &AtomicBool,
+        status_callback: impl FnMut(DownloadStatus),
+    ) -> Result<Vec<MediaInfo>> {
+        // Watchers of the output directory only ever see finished files
+        let staging_dir = self.config.staging_dir();
+        fs::create_dir_all(&staging_dir)
+            .with_context(|| format!("Failed to create {}", staging_dir.display()))?;
+        let output_template = staging_dir
             .join("%(title)s_%(upload_date)s_%(id)s.%(ext)s")
             .to_string_lossy()
             .to_string();
 
-        let child = Command::new("yt-dlp")
+        let proxy = self.proxies.pick(url);
+        let mut command = self.command(proxy.as_deref(), user_agent);
+        command
             .arg(url)
             .arg("-o")
             .arg(&output_template)
+            .arg("--continue")  // Resume the .part file of an interrupted job
             .arg("--newline")  // Force progress on new lines
-            .arg("--no-check-certificates")  // Skip SSL verification
-            .arg("--user-agent")
-            .arg("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36")
+            .arg("--progress")  // Keep progress output even though --print implies --quiet
+            .arg("--print")
+            .arg("after_move:%(.{filepath,uploader,description})j")
             .arg("--progress-template")
-            .arg("download:[%(progress.downloaded_bytes)s/%(progress.total_bytes)s][%(progress.speed)s][ETA:%(progress.eta)s]")
+            .arg("download:[%(progress.downloaded_bytes)s/%(progress.total_bytes)s][%(progress.speed)s][ETA:%(progress.eta)s]");
+
+        // Its own process group, so cancelling can take ffmpeg down with it
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let child = command
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
             .context("Failed to start yt-dlp")?;
 
-        DownloadUtils::handle_download_process(child, status_callback, output_template)
+        let result = DownloadUtils::handle_download_process(child, cancel, status_callback);
+        match &result {
+            Err(e) if e.is::<Cancelled>() => {}
+            Err(e) => self.proxies.report(proxy.as_deref(), ErrorClass::classify(&e.to_string()) == ErrorClass::RateLimited),
+            Ok(_) => self.proxies.report(proxy.as_deref(), false),
+        }
+        result
+    }
+
+    /// Moves a finished download out of the staging directory into the
+    /// output directory, replacing any earlier copy in one step
+    fn publish(&self, mut info: MediaInfo) -> Result<MediaInfo> {
+        let staged = PathBuf::from(&info.filepath);
+        let file_name = staged.file_name().context("yt-dlp reported a download without a file name")?;
+        let target = self.config.output_dir.join(file_name);
+        fs::rename(&staged, &target)
+            .with_context(|| format!("Failed to move {} into {}", staged.display(), self.config.output_dir.display()))?;
+        info.filepath = target.to_string_lossy().to_string();
+        Ok(info)
+    }
+
+    /// URLs of the newest `limit` posts on a profile or hashtag page,
+    /// without downloading anything
+    pub fn list_posts(&self, feed_url: &str, limit: usize) -> Result<Vec<String>> {
+        let proxy = self.proxies.pick(feed_url);
+        // Subscription checks don't take a turn from the downloads' rotation
+        let output = self.command(proxy.as_deref(), self.user_agents.first())
+            .arg(feed_url)
+            .arg("--flat-playlist")
+            .arg("--playlist-end")
+            .arg(limit.to_string())
+            .arg("--print")
+            .arg("url")
+            .stdin(Stdio::null())
+            .output()
+            .context("Failed to start yt-dlp")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let error = stderr
+                .lines()
+                .rev()
+                .find(|line| line.starts_with("ERROR:"))
+                .unwrap_or("yt-dlp failed without an error message");
+            self.proxies.report(proxy.as_deref(), ErrorClass::classify(error) == ErrorClass::RateLimited);
+            bail!("{}", error.trim_start_matches("ERROR:").trim());
+        }
+
+        self.proxies.report(proxy.as_deref(), false);
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with("http"))
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// yt-dlp with the options every request shares, going through `proxy`
+    fn command(&self, proxy: Option<&str>, user_agent: &str) -> Command {
+        let mut command = self.backend.command();
+        command.arg("--user-agent").arg(user_agent);
+
+        // Cookies from the browser extension unlock private and login-walled posts
+        let cookies = self.config.cookies_path();
+        if cookies.exists() {
+            command.arg("--cookies").arg(cookies);
+        }
+        if let Some(proxy) = proxy {
+            command.arg("--proxy").arg(proxy);
+        }
+        self.config.settings.tls.apply(&mut command);
+        self.config.settings.rate_limit.apply(&mut command);
+        command
     }
 } 
\ No newline at end of file
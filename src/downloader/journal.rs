@@ -0,0 +1,374 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use super::error::QueueInUse;
+use super::queue::Job;
+
+/// Times yt-dlp may crash on one job before it is given up on
+pub const MAX_CRASHES: u32 = 3;
+
+/// Finished entries the file may collect before it is rewritten
+const COMPACT_AFTER: usize = 500;
+
+/// One line of the journal
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Entry {
+    Queued {
+        job: Job,
+        /// Carried over when the journal is compacted
+        #[serde(default, skip_serializing_if = "is_zero")]
+        crashes: u32,
+    },
+    Started { id: u64 },
+    /// The process shut down cleanly while the job ran
+    Released { id: u64 },
+    /// yt-dlp crashed; the job goes back in the queue
+    Crashed { id: u64 },
+    Finished { id: u64, result: String },
+    Removed { id: u64 },
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+struct Record {
+    job: Job,
+    /// Times yt-dlp crashed on the job
+    crashes: u32,
+    running: bool,
+}
+
+/// Jobs an earlier run left unfinished
+pub struct Recovered {
+    /// In queue order; those that were running when the process died come first
+    pub jobs: Vec<Job>,
+    /// How many of `jobs` were running when the last process stopped
+    pub interrupted: usize,
+    /// Jobs yt-dlp crashed on `MAX_CRASHES` times, not to be run again
+    pub crashed: Vec<Job>,
+}
+
+/// Append-only log of queue changes, replayed on startup so pending and
+/// interrupted jobs survive the terminal closing or the process dying
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+    /// Held for as long as the journal is open, so two processes never
+    /// run the same jobs
+    _lock: File,
+    live: Vec<Record>,
+    dead_entries: usize,
+}
+
+impl Journal {
+    /// Fails with `QueueInUse` while another process has the journal open
+    pub fn open(path: PathBuf) -> Result<(Self, Recovered)> {
+        let lock = lock(&path)?;
+        let mut live: Vec<Record> = Vec::new();
+
+        match File::open(&path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    // A crash can leave the last line half-written
+                    let Ok(entry) = serde_json::from_str::<Entry>(&line?) else {
+                        continue;
+                    };
+                    apply(&mut live, entry);
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+
+        let (crashed, mut kept): (Vec<Record>, Vec<Record>) =
+            live.into_iter().partition(|record| record.crashes >= MAX_CRASHES);
+        // Resume what was running before starting anything new
+        kept.sort_by_key(|record| !record.running);
+        let interrupted = kept.iter().filter(|record| record.running).count();
+        for record in &mut kept {
+            record.running = false;
+        }
+
+        let recovered = Recovered {
+            jobs: kept.iter().map(|record| record.job.clone()).collect(),
+            interrupted,
+            crashed: crashed.into_iter().map(|record| record.job).collect(),
+        };
+
+        let file = rewrite(&path, &kept)?;
+        let journal = Self { path, file, _lock: lock, live: kept, dead_entries: 0 };
+        Ok((journal, recovered))
+    }
+
+    pub fn queued(&mut self, job: &Job) {
+        self.append(Entry::Queued { job: job.clone(), crashes: 0 });
+    }
+
+    pub fn started(&mut self, id: u64) {
+        self.append(Entry::Started { id });
+    }
+
+    pub fn released(&mut self, id: u64) {
+        self.append(Entry::Released { id });
+    }
+
+    /// Records a crash and returns how many the job has had
+    pub fn crashed(&mut self, id: u64) -> u32 {
+        self.append(Entry::Crashed { id });
+        self.live.iter().find(|record| record.job.id == id).map_or(0, |record| record.crashes)
+    }
+
+    pub fn finished(&mut self, id: u64, result: &str) {
+        self.append(Entry::Finished { id, result: result.to_string() });
+    }
+
+    pub fn removed(&mut self, id: u64) {
+        self.append(Entry::Removed { id });
+    }
+
+    fn append(&mut self, entry: Entry) {
+        if matches!(entry, Entry::Finished { .. } | Entry::Removed { .. }) {
+            self.dead_entries += 1;
+        }
+        let line = serde_json::to_string(&entry).map(|json| json + "\n");
+        apply(&mut self.live, entry);
+
+        let written = line
+            .map_err(anyhow::Error::from)
+            .and_then(|line| Ok(self.file.write_all(line.as_bytes())?));
+        if let Err(e) = written {
+            eprintln!("Failed to write the queue journal {}: {:#}", self.path.display(), e);
+        }
+
+        if self.dead_entries >= COMPACT_AFTER {
+            match rewrite(&self.path, &self.live) {
+                Ok(file) => {
+                    self.file = file;
+                    self.dead_entries = 0;
+                }
+                Err(e) => eprintln!("Failed to compact the queue journal {}: {:#}", self.path.display(), e),
+            }
+        }
+    }
+}
+
+fn apply(live: &mut Vec<Record>, entry: Entry) {
+    match entry {
+        Entry::Queued { job, crashes } => live.push(Record { job, crashes, running: false }),
+        Entry::Started { id } => {
+            if let Some(record) = live.iter_mut().find(|record| record.job.id == id) {
+                record.running = true;
+            }
+        }
+        Entry::Released { id } => {
+            if let Some(record) = live.iter_mut().find(|record| record.job.id == id) {
+                record.running = false;
+            }
+        }
+        Entry::Crashed { id } => {
+            if let Some(record) = live.iter_mut().find(|record| record.job.id == id) {
+                record.crashes += 1;
+                record.running = false;
+            }
+        }
+        Entry::Finished { id, .. } | Entry::Removed { id } => live.retain(|record| record.job.id != id),
+    }
+}
+
+/// Takes the lock file beside the journal at `path`
+fn lock(path: &Path) -> Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let lock_path = path.with_extension("lock");
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+    match try_lock(&file) {
+        Ok(true) => Ok(file),
+        Ok(false) => Err(QueueInUse.into()),
+        Err(e) => Err(e).with_context(|| format!("Failed to lock {}", lock_path.display())),
+    }
+}
+
+/// Takes an exclusive lock on `file` without waiting; `false` if another
+/// process holds it. The lock goes with the file descriptor.
+#[cfg(unix)]
+fn try_lock(file: &File) -> std::io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: the descriptor stays open for as long as `file` is borrowed
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+    let error = std::io::Error::last_os_error();
+    if error.kind() == std::io::ErrorKind::WouldBlock {
+        Ok(false)
+    } else {
+        Err(error)
+    }
+}
+
+#[cfg(not(unix))]
+fn try_lock(_file: &File) -> std::io::Result<bool> {
+    Ok(true)
+}
+
+/// Replaces the journal with just the live jobs, in one step so a crash
+/// never leaves it half-written, and returns it open for appending
+fn rewrite(path: &Path, live: &[Record]) -> Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp = path.with_extension("jsonl.tmp");
+    let mut contents = String::new();
+    for record in live {
+        let entry = Entry::Queued { job: record.job.clone(), crashes: record.crashes };
+        contents.push_str(&serde_json::to_string(&entry)?);
+        contents.push('\n');
+        if record.running {
+            contents.push_str(&serde_json::to_string(&Entry::Started { id: record.job.id })?);
+            contents.push('\n');
+        }
+    }
+    fs::write(&temp, contents).with_context(|| format!("Failed to write {}", temp.display()))?;
+    fs::rename(&temp, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+
+    OpenOptions::new()
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("instadown-journal-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("queue.jsonl")
+    }
+
+    fn job(id: u64) -> Job {
+        Job { id, url: format!("https://www.instagram.com/p/{}/", id), window: None, user_agent: None }
+    }
+
+    fn ids(jobs: &[Job]) -> Vec<u64> {
+        jobs.iter().map(|job| job.id).collect()
+    }
+
+    #[test]
+    fn resumes_interrupted_jobs_first() {
+        let path = journal_path("resume");
+        let (mut journal, recovered) = Journal::open(path.clone()).unwrap();
+        assert!(recovered.jobs.is_empty());
+        for id in 1..=3 {
+            journal.queued(&job(id));
+        }
+        journal.started(1);
+        journal.finished(1, "completed");
+        journal.started(3);
+        journal.removed(2);
+        drop(journal);
+
+        let (_journal, recovered) = Journal::open(path.clone()).unwrap();
+        assert_eq!(ids(&recovered.jobs), [3]);
+        assert_eq!(recovered.interrupted, 1);
+        assert!(recovered.crashed.is_empty());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn clean_shutdowns_are_not_crashes() {
+        let path = journal_path("released");
+        let (mut journal, _) = Journal::open(path.clone()).unwrap();
+        journal.queued(&job(1));
+        for _ in 0..MAX_CRASHES + 1 {
+            journal.started(1);
+            journal.released(1);
+        }
+        drop(journal);
+
+        let (_journal, recovered) = Journal::open(path.clone()).unwrap();
+        assert_eq!(ids(&recovered.jobs), [1]);
+        assert_eq!(recovered.interrupted, 0);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn gives_up_after_repeated_crashes() {
+        let path = journal_path("crashes");
+        let (mut journal, _) = Journal::open(path.clone()).unwrap();
+        journal.queued(&job(1));
+        journal.queued(&job(2));
+        for crash in 1..MAX_CRASHES {
+            journal.started(1);
+            assert_eq!(journal.crashed(1), crash);
+        }
+        drop(journal);
+
+        // The count survives the journal being rewritten on open
+        let (mut journal, recovered) = Journal::open(path.clone()).unwrap();
+        assert_eq!(ids(&recovered.jobs), [1, 2]);
+        journal.started(1);
+        assert_eq!(journal.crashed(1), MAX_CRASHES);
+        drop(journal);
+
+        let (_journal, recovered) = Journal::open(path.clone()).unwrap();
+        assert_eq!(ids(&recovered.jobs), [2]);
+        assert_eq!(ids(&recovered.crashed), [1]);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn skips_a_half_written_line() {
+        let path = journal_path("torn");
+        let (mut journal, _) = Journal::open(path.clone()).unwrap();
+        journal.queued(&job(1));
+        drop(journal);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"op\":\"queued\",\"job\":{\"id\":2,").unwrap();
+
+        let (_journal, recovered) = Journal::open(path.clone()).unwrap();
+        assert_eq!(ids(&recovered.jobs), [1]);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn compacts_finished_entries() {
+        let path = journal_path("compact");
+        let (mut journal, _) = Journal::open(path.clone()).unwrap();
+        journal.queued(&job(1));
+        for id in 2..COMPACT_AFTER as u64 + 2 {
+            journal.queued(&job(id));
+            journal.finished(id, "completed");
+        }
+        journal.queued(&job(9999));
+
+        let lines = fs::read_to_string(&path).unwrap().lines().count();
+        assert_eq!(lines, 2);
+        drop(journal);
+        let (_journal, recovered) = Journal::open(path.clone()).unwrap();
+        assert_eq!(ids(&recovered.jobs), [1, 9999]);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn only_one_process_runs_the_queue() {
+        let path = journal_path("lock");
+        let (journal, _) = Journal::open(path.clone()).unwrap();
+        assert!(Journal::open(path.clone()).err().unwrap().is::<QueueInUse>());
+        drop(journal);
+        assert!(Journal::open(path.clone()).is_ok());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -1,4 +1,14 @@
+pub mod backend;
+pub mod cookies;
+pub mod error;
 pub mod instagram;
+pub mod journal;
+pub mod proxy;
+pub mod queue;
+pub mod throttle;
+pub mod tls;
+pub mod user_agent;
 pub mod utils;
+pub mod verify;
 
 pub use instagram::InstagramDownloader; 
\ No newline at end of file
@@ -0,0 +1,499 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::core::disk::free_space;
+use crate::core::schedule::{ScheduleConfig, TimeWindow};
+use crate::core::storage::StorageConfig;
+use crate::hooks::{HookEvent, HookPayload, HookRunner};
+use crate::library::Library;
+use crate::ui::app::{AttemptStatus, Download, DownloadStatus};
+use super::error::{Cancelled, Crashed, ErrorClass};
+use super::instagram::{InstagramDownloader, MediaInfo};
+use super::journal::{Journal, MAX_CRASHES};
+use super::throttle::Throttle;
+use super::verify::VerificationFailed;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    pub url: String,
+    /// Only start within this daily window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<TimeWindow>,
+    /// Sent by the job's download once it has started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum JobOutcome {
+    /// One file per entry of the post
+    Completed { files: Vec<MediaInfo> },
+    Failed { class: ErrorClass, message: String },
+    Cancelled,
+}
+
+impl JobOutcome {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Completed { .. } => "completed",
+            Self::Failed { .. } => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    /// "name.mp4: <warning>" for each file verification kept despite a doubt
+    pub fn warnings(&self) -> Vec<String> {
+        let Self::Completed { files } = self else {
+            return Vec::new();
+        };
+        files
+            .iter()
+            .filter_map(|info| {
+                let name = PathBuf::from(&info.filepath).file_name()?.to_string_lossy().to_string();
+                info.warning.as_ref().map(|warning| format!("{}: {}", name, warning))
+            })
+            .collect()
+    }
+}
+
+/// What `DownloadQueue::restore` found from the last run
+#[derive(Default)]
+pub struct Restored {
+    /// Jobs queued again, including the interrupted ones
+    pub resumed: usize,
+    /// Jobs that were running when the last process stopped
+    pub interrupted: usize,
+    /// Jobs given up on and recorded as failed
+    pub crashed: usize,
+}
+
+impl Restored {
+    /// "Resuming 3 jobs from the last run (1 interrupted)", or `None` if
+    /// there was nothing to pick up
+    pub fn summary(&self) -> Option<String> {
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        let mut parts = Vec::new();
+        if self.resumed > 0 {
+            let mut part = format!("Resuming {} job{} from the last run", self.resumed, plural(self.resumed));
+            if self.interrupted > 0 {
+                part.push_str(&format!(" ({} interrupted)", self.interrupted));
+            }
+            parts.push(part);
+        }
+        if self.crashed > 0 {
+            parts.push(format!(
+                "{} job{} crashed {} times and will not be retried",
+                self.crashed,
+                plural(self.crashed),
+                MAX_CRASHES
+            ));
+        }
+        (!parts.is_empty()).then(|| parts.join("; "))
+    }
+}
+
+pub enum QueueEvent {
+    Started,
+    Progress(DownloadStatus),
+    Finished {
+        job: Job,
+        outcome: JobOutcome,
+        duration: Duration,
+    },
+    HookFailed(String),
+    /// yt-dlp crashed and the job went back to the front of the queue
+    Retrying { job: Job, message: String },
+}
+
+struct RunningJob {
+    job: Job,
+    cancel: Arc<AtomicBool>,
+    started: Instant,
+}
+
+enum WorkerMessage {
+    Progress(DownloadStatus),
+    Done(anyhow::Result<Vec<MediaInfo>>),
+}
+
+/// Finished downloads the size estimate averages over
+const SIZES_KEPT: usize = 20;
+
+/// FIFO of download jobs, run one at a time on a background thread. Jobs
+/// outside their window, or any job during quiet hours, wait their turn, as
+/// do jobs the rate limit holds back. The queue pauses while the download
+/// directory is low on space.
+pub struct DownloadQueue {
+    downloader: Arc<InstagramDownloader>,
+    pending: VecDeque<Job>,
+    running: Option<RunningJob>,
+    next_id: u64,
+    worker_tx: Sender<WorkerMessage>,
+    worker_rx: Receiver<WorkerMessage>,
+    hooks: HookRunner,
+    schedule: ScheduleConfig,
+    throttle: Throttle,
+    storage: StorageConfig,
+    /// Sizes of the latest downloads, to estimate the next one
+    sizes: VecDeque<u64>,
+    /// `(free, needed)` bytes when the last ready job was held back for space
+    low_space: Option<(u64, u64)>,
+    /// Records queue changes, if unfinished jobs should outlive the process
+    journal: Option<Journal>,
+}
+
+impl DownloadQueue {
+    pub fn new(downloader: InstagramDownloader) -> Self {
+        let (worker_tx, worker_rx) = mpsc::channel();
+        let hooks = HookRunner::new(&downloader.config().settings.hooks);
+        let schedule = downloader.config().settings.schedule.clone();
+        let throttle = Throttle::new(&downloader.config().settings.rate_limit);
+        let storage = downloader.config().settings.storage.clone();
+        Self {
+            downloader: Arc::new(downloader),
+            pending: VecDeque::new(),
+            running: None,
+            next_id: 1,
+            worker_tx,
+            worker_rx,
+            hooks,
+            schedule,
+            throttle,
+            storage,
+            sizes: VecDeque::new(),
+            low_space: None,
+            journal: None,
+        }
+    }
+
+    /// Replays the journal at `path` left by an earlier run, then keeps it
+    /// up to date. Jobs that were running go first and pick up their partial
+    /// files; one yt-dlp crashed on `MAX_CRASHES` times is recorded in
+    /// history as crashed instead of being run again. Fails with
+    /// `QueueInUse` while another process runs the queue.
+    pub fn restore(&mut self, path: PathBuf, library: &mut Library) -> anyhow::Result<Restored> {
+        let (journal, recovered) = Journal::open(path)?;
+
+        for job in &recovered.crashed {
+            let mut download = Download::attempt(
+                job.url.clone(),
+                AttemptStatus::Failed { class: ErrorClass::Crashed },
+                Duration::ZERO,
+            );
+            download.error = Some(format!("yt-dlp crashed during this download {} times", MAX_CRASHES));
+            library.record_attempt(&download)?;
+        }
+
+        let restored = Restored {
+            resumed: recovered.jobs.len(),
+            interrupted: recovered.interrupted,
+            crashed: recovered.crashed.len(),
+        };
+        let highest = recovered.jobs.iter().chain(&recovered.crashed).map(|job| job.id).max();
+        self.next_id = self.next_id.max(highest.map_or(1, |id| id + 1));
+        self.pending.extend(recovered.jobs);
+        self.journal = Some(journal);
+        Ok(restored)
+    }
+
+    pub fn enqueue(&mut self, url: String, window: Option<TimeWindow>) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.hooks.fire(HookPayload::new(HookEvent::Queued, id, &url));
+        let job = Job { id, url, window, user_agent: None };
+        if let Some(journal) = &mut self.journal {
+            journal.queued(&job);
+        }
+        self.pending.push_back(job);
+        id
+    }
+
+    /// Queues `url` unless it is already queued or, without `force`, the
+    /// library already has it on disk. Skips of downloaded posts are recorded
+    /// in history; returns the job id if queued.
+    pub fn enqueue_unless_downloaded(
+        &mut self,
+        library: &mut Library,
+        url: String,
+        force: bool,
+        window: Option<TimeWindow>,
+    ) -> anyhow::Result<Option<u64>> {
+        if self.current().into_iter().chain(&self.pending).any(|job| job.url == url) {
+            return Ok(None);
+        }
+        if !force && library.already_downloaded(&url)? {
+            library.record_attempt(&Download::attempt(url, AttemptStatus::SkippedDuplicate, Duration::ZERO))?;
+            return Ok(None);
+        }
+        Ok(Some(self.enqueue(url, window)))
+    }
+
+    pub fn downloader(&self) -> &InstagramDownloader {
+        &self.downloader
+    }
+
+    /// The downloader, for other parts that talk to Instagram
+    pub fn shared_downloader(&self) -> Arc<InstagramDownloader> {
+        Arc::clone(&self.downloader)
+    }
+
+    /// Number of jobs waiting to start
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = &Job> {
+        self.pending.iter()
+    }
+
+    /// Drops a job that hasn't started yet
+    pub fn remove(&mut self, id: u64) -> bool {
+        let before = self.pending.len();
+        self.pending.retain(|job| job.id != id);
+        let removed = self.pending.len() != before;
+        if let Some(journal) = self.journal.as_mut().filter(|_| removed) {
+            journal.removed(id);
+        }
+        removed
+    }
+
+    /// When the next job may start, if every pending job has to wait for
+    /// quiet hours to end or for its window to open
+    pub fn waiting_until(&self) -> Option<DateTime<Local>> {
+        if self.running.is_some() {
+            return None;
+        }
+        let now = Local::now();
+        self.pending
+            .iter()
+            .map(|job| self.schedule.ready_at(job.window, now))
+            .min()
+            .filter(|ready| *ready > now)
+    }
+
+    /// How long until the rate limit lets the next job start, if that is
+    /// all that holds it back
+    pub fn throttled_for(&self) -> Option<Duration> {
+        if self.running.is_some() {
+            return None;
+        }
+        let now = Local::now();
+        self.pending
+            .iter()
+            .filter(|job| self.schedule.ready_at(job.window, now) <= now)
+            .map(|job| self.throttle.ready_at(&job.url).saturating_duration_since(Instant::now()))
+            .min()
+            .filter(|wait| !wait.is_zero())
+    }
+
+    /// `(free, needed)` bytes while the queue is paused for disk space
+    pub fn low_on_space(&self) -> Option<(u64, u64)> {
+        self.low_space.filter(|_| self.running.is_none())
+    }
+
+    /// Room the next download is expected to take: the recent average, or
+    /// the configured guess before any have finished
+    fn size_estimate(&self) -> u64 {
+        match self.sizes.len() as u64 {
+            0 => self.storage.job_estimate(),
+            count => self.sizes.iter().sum::<u64>() / count,
+        }
+    }
+
+    /// Whether the download directory can take another job and still keep
+    /// `min_free_mb` free. Platforms that can't tell always have room.
+    fn has_room(&mut self) -> bool {
+        let needed = self.storage.min_free() + self.size_estimate();
+        self.low_space = free_space(&self.downloader.config().output_dir)
+            .filter(|free| *free < needed)
+            .map(|free| (free, needed));
+        self.low_space.is_none()
+    }
+
+    pub fn current(&self) -> Option<&Job> {
+        self.running.as_ref().map(|running| &running.job)
+    }
+
+    /// Asks the running job to stop; its `Finished` event reports `Cancelled`
+    pub fn cancel_current(&mut self) -> bool {
+        match &self.running {
+            Some(running) => {
+                running.cancel.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Collects worker updates and starts the next job once the previous
+    /// one has finished. Call regularly from the owner's event loop.
+    pub fn poll(&mut self) -> Vec<QueueEvent> {
+        let mut events = Vec::new();
+
+        while let Ok(message) = self.worker_rx.try_recv() {
+            match message {
+                WorkerMessage::Progress(status) => events.push(QueueEvent::Progress(status)),
+                WorkerMessage::Done(result) => {
+                    let Some(running) = self.running.take() else {
+                        continue;
+                    };
+                    let outcome = outcome_from(result);
+                    let duration = running.started.elapsed();
+                    self.throttle.finished();
+                    if let JobOutcome::Failed { class: ErrorClass::Crashed, message } = &outcome {
+                        // Without a journal there is nothing to count against
+                        let crashes = self.journal.as_mut().map_or(MAX_CRASHES, |journal| journal.crashed(running.job.id));
+                        if crashes < MAX_CRASHES {
+                            events.push(QueueEvent::Retrying { job: running.job.clone(), message: message.clone() });
+                            self.pending.push_front(running.job);
+                            continue;
+                        }
+                    }
+                    if let JobOutcome::Completed { files } = &outcome {
+                        self.sizes.push_back(total_size(files));
+                        if self.sizes.len() > SIZES_KEPT {
+                            self.sizes.pop_front();
+                        }
+                    }
+                    self.fire_finished(&running.job, &outcome, duration);
+                    if let Some(journal) = &mut self.journal {
+                        journal.finished(running.job.id, outcome.label());
+                    }
+                    events.push(QueueEvent::Finished {
+                        job: running.job,
+                        outcome,
+                        duration,
+                    });
+                }
+            }
+        }
+
+        if self.running.is_none() {
+            let now = Local::now();
+            let ready = self.pending.iter().position(|job| {
+                self.schedule.ready_at(job.window, now) <= now && self.throttle.ready_at(&job.url) <= Instant::now()
+            });
+            if ready.is_none() {
+                self.low_space = None;
+            }
+            let ready = ready.filter(|_| self.has_room());
+            if let Some(job) = ready.and_then(|index| self.pending.remove(index)) {
+                self.throttle.started(&job.url);
+                self.hooks.fire(HookPayload::new(HookEvent::Started, job.id, &job.url));
+                events.push(QueueEvent::Started);
+                if let Some(journal) = &mut self.journal {
+                    journal.started(job.id);
+                }
+                self.start(job);
+            }
+        }
+
+        events.extend(self.hooks.failures().into_iter().map(QueueEvent::HookFailed));
+        events
+    }
+
+    fn fire_finished(&self, job: &Job, outcome: &JobOutcome, duration: Duration) {
+        let mut payload = match outcome {
+            JobOutcome::Completed { files } => {
+                let mut payload = HookPayload::new(HookEvent::Completed, job.id, &job.url);
+                payload.size = Some(total_size(files));
+                payload.file = files.first().map(|info| info.filepath.clone());
+                payload.files = files.iter().map(|info| info.filepath.clone()).collect();
+                payload.uploader = files.first().and_then(|info| info.uploader.clone());
+                payload.caption = files.first().and_then(|info| info.description.clone());
+                payload
+            }
+            JobOutcome::Failed { class, message } => {
+                let mut payload = HookPayload::new(HookEvent::Failed, job.id, &job.url);
+                payload.error = Some(message.clone());
+                payload.error_class = Some(class.label().to_string());
+                payload
+            }
+            JobOutcome::Cancelled => return,
+        };
+        payload.duration_ms = Some(duration.as_millis() as u64);
+        self.hooks.fire(payload);
+    }
+
+    fn start(&mut self, mut job: Job) {
+        let user_agent = self.downloader.next_user_agent();
+        job.user_agent = Some(user_agent.clone());
+        let cancel = Arc::new(AtomicBool::new(false));
+        let downloader = Arc::clone(&self.downloader);
+        let tx = self.worker_tx.clone();
+        let url = job.url.clone();
+        let worker_cancel = Arc::clone(&cancel);
+
+        thread::spawn(move || {
+            let result = downloader.download(&url, &user_agent, &worker_cancel, |status| {
+                let _ = tx.send(WorkerMessage::Progress(status));
+            });
+            let _ = tx.send(WorkerMessage::Done(result));
+        });
+
+        self.running = Some(RunningJob {
+            job,
+            cancel,
+            started: Instant::now(),
+        });
+    }
+}
+
+/// How long a shutdown waits for the running download to stop
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl Drop for DownloadQueue {
+    /// Stops the running download, which is resumed on the next start.
+    /// yt-dlp has its own process group, so it wouldn't die with us.
+    fn drop(&mut self) {
+        let Some(running) = self.running.take() else {
+            return;
+        };
+        running.cancel.store(true, Ordering::Relaxed);
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        while let Ok(message) = self.worker_rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            if matches!(message, WorkerMessage::Done(_)) {
+                break;
+            }
+        }
+        if let Some(journal) = &mut self.journal {
+            journal.released(running.job.id);
+        }
+    }
+}
+
+/// Bytes on disk across a post's files
+fn total_size(files: &[MediaInfo]) -> u64 {
+    files.iter().filter_map(|info| std::fs::metadata(&info.filepath).ok()).map(|meta| meta.len()).sum()
+}
+
+fn outcome_from(result: anyhow::Result<Vec<MediaInfo>>) -> JobOutcome {
+    match result {
+        Ok(files) => JobOutcome::Completed { files },
+        Err(e) if e.is::<Cancelled>() => JobOutcome::Cancelled,
+        Err(e) if e.is::<VerificationFailed>() => JobOutcome::Failed {
+            class: ErrorClass::Corrupt,
+            message: e.to_string(),
+        },
+        Err(e) if e.is::<Crashed>() => JobOutcome::Failed {
+            class: ErrorClass::Crashed,
+            message: e.to_string(),
+        },
+        Err(e) => {
+            let message = e.to_string();
+            JobOutcome::Failed {
+                class: ErrorClass::classify(&message),
+                message,
+            }
+        }
+    }
+}
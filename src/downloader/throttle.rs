@@ -0,0 +1,217 @@
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// The `[rate_limit]` section of `config.toml`. Nothing is limited unless set.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Jobs started per minute against any one host
+    pub requests_per_minute: Option<u32>,
+    /// Seconds to wait after a job finishes before starting the next
+    pub min_delay_secs: u64,
+    /// yt-dlp `--sleep-requests`: seconds between its requests during extraction
+    pub sleep_requests: Option<f64>,
+    /// yt-dlp `--sleep-interval`: seconds to sleep before each download, the
+    /// lower bound when `max_sleep_interval` is set
+    pub sleep_interval: Option<f64>,
+    /// yt-dlp `--max-sleep-interval`: picks a random sleep up to this
+    pub max_sleep_interval: Option<f64>,
+    /// yt-dlp `--limit-rate`: bandwidth cap such as "500K" or "2M" per second
+    pub limit_rate: Option<String>,
+}
+
+impl RateLimitConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.requests_per_minute == Some(0) {
+            bail!("`requests_per_minute` must be at least 1");
+        }
+        for (name, value) in [
+            ("sleep_requests", self.sleep_requests),
+            ("sleep_interval", self.sleep_interval),
+            ("max_sleep_interval", self.max_sleep_interval),
+        ] {
+            if value.is_some_and(|seconds| !seconds.is_finite() || seconds < 0.0) {
+                bail!("`{}` must be a number of seconds", name);
+            }
+        }
+        match (self.sleep_interval, self.max_sleep_interval) {
+            (None, Some(_)) => bail!("`max_sleep_interval` needs `sleep_interval`"),
+            (Some(min), Some(max)) if max < min => bail!("`max_sleep_interval` is less than `sleep_interval`"),
+            _ => {}
+        }
+        if let Some(rate) = &self.limit_rate {
+            let (number, unit) = rate.split_at(rate.trim_end_matches(|c: char| c.is_ascii_alphabetic()).len());
+            if number.parse::<f64>().map_or(true, |n| n <= 0.0)
+                || !matches!(unit.to_ascii_uppercase().as_str(), "" | "K" | "M" | "G")
+            {
+                bail!("`limit_rate` should look like 500K or 2M, got {:?}", rate);
+            }
+        }
+        Ok(())
+    }
+
+    /// Passes the sleep and bandwidth options on to yt-dlp
+    pub fn apply(&self, command: &mut Command) {
+        if let Some(seconds) = self.sleep_requests {
+            command.arg("--sleep-requests").arg(seconds.to_string());
+        }
+        if let Some(seconds) = self.sleep_interval {
+            command.arg("--sleep-interval").arg(seconds.to_string());
+        }
+        if let Some(seconds) = self.max_sleep_interval {
+            command.arg("--max-sleep-interval").arg(seconds.to_string());
+        }
+        if let Some(rate) = &self.limit_rate {
+            command.arg("--limit-rate").arg(rate);
+        }
+    }
+
+    /// "10/min per host · 5s between jobs · sleep 1–5s · 2M/s max", or
+    /// `None` when nothing is limited
+    pub fn summary(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(limit) = self.requests_per_minute {
+            parts.push(format!("{}/min per host", limit));
+        }
+        if self.min_delay_secs > 0 {
+            parts.push(format!("{}s between jobs", self.min_delay_secs));
+        }
+        if let Some(seconds) = self.sleep_requests {
+            parts.push(format!("{}s between requests", seconds));
+        }
+        match (self.sleep_interval, self.max_sleep_interval) {
+            (Some(min), Some(max)) => parts.push(format!("sleep {}–{}s", min, max)),
+            (Some(min), None) => parts.push(format!("sleep {}s", min)),
+            _ => {}
+        }
+        if let Some(rate) = &self.limit_rate {
+            parts.push(format!("{}/s max", rate));
+        }
+        (!parts.is_empty()).then(|| parts.join(" · "))
+    }
+}
+
+/// Keeps job starts within `requests_per_minute` per host and
+/// `min_delay_secs` apart
+pub struct Throttle {
+    per_minute: Option<u32>,
+    min_delay: Duration,
+    starts: HashMap<String, VecDeque<Instant>>,
+    last_finished: Option<Instant>,
+}
+
+impl Throttle {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            per_minute: config.requests_per_minute,
+            min_delay: Duration::from_secs(config.min_delay_secs),
+            starts: HashMap::new(),
+            last_finished: None,
+        }
+    }
+
+    /// Earliest a job for `url` may start
+    pub fn ready_at(&self, url: &str) -> Instant {
+        let mut ready = self
+            .last_finished
+            .map_or_else(Instant::now, |finished| finished + self.min_delay);
+
+        if let (Some(limit), Some(starts)) = (self.per_minute, self.starts.get(host(url))) {
+            // The window frees up a slot when the oldest start in it is a minute old
+            if starts.len() >= limit as usize {
+                let oldest = starts[starts.len() - limit as usize];
+                ready = ready.max(oldest + MINUTE);
+            }
+        }
+        ready
+    }
+
+    pub fn started(&mut self, url: &str) {
+        let now = Instant::now();
+        let starts = self.starts.entry(host(url).to_string()).or_default();
+        starts.push_back(now);
+        while starts.front().is_some_and(|start| now.duration_since(*start) >= MINUTE) {
+            starts.pop_front();
+        }
+    }
+
+    pub fn finished(&mut self) {
+        self.last_finished = Some(Instant::now());
+    }
+}
+
+/// "www.instagram.com" from "https://www.instagram.com/p/XXXX/"
+fn host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split(['/', '?', '#']).next().unwrap_or(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POST: &str = "https://www.instagram.com/p/XXXX/";
+
+    fn throttle(requests_per_minute: Option<u32>, min_delay_secs: u64) -> Throttle {
+        Throttle::new(&RateLimitConfig { requests_per_minute, min_delay_secs, ..Default::default() })
+    }
+
+    #[test]
+    fn unlimited_is_ready_now() {
+        let mut throttle = throttle(None, 0);
+        for _ in 0..10 {
+            throttle.started(POST);
+        }
+        throttle.finished();
+        assert!(throttle.ready_at(POST) <= Instant::now());
+    }
+
+    #[test]
+    fn waits_min_delay_after_a_job_finishes() {
+        let mut throttle = throttle(None, 30);
+        let before = Instant::now();
+        assert!(throttle.ready_at(POST) <= Instant::now());
+
+        throttle.finished();
+        let ready = throttle.ready_at(POST);
+        assert!(ready >= before + Duration::from_secs(30));
+        assert!(ready <= Instant::now() + Duration::from_secs(30));
+    }
+
+    #[test]
+    fn limits_starts_per_host() {
+        let mut throttle = throttle(Some(2), 0);
+        let before = Instant::now();
+        throttle.started(POST);
+        assert!(throttle.ready_at(POST) <= Instant::now());
+
+        throttle.started("https://www.instagram.com/reel/YYYY/?igsh=1");
+        let ready = throttle.ready_at(POST);
+        assert!(ready >= before + MINUTE);
+        assert!(ready <= Instant::now() + MINUTE);
+
+        // Other hosts have their own window
+        assert!(throttle.ready_at("https://instagr.am/p/XXXX/") <= Instant::now());
+    }
+
+    #[test]
+    fn takes_the_later_of_both_limits() {
+        let mut throttle = throttle(Some(1), 120);
+        let before = Instant::now();
+        throttle.started(POST);
+        throttle.finished();
+        assert!(throttle.ready_at(POST) >= before + Duration::from_secs(120));
+    }
+
+    #[test]
+    fn host_ignores_scheme_path_and_query() {
+        assert_eq!(host(POST), "www.instagram.com");
+        assert_eq!(host("instagram.com?x=1"), "instagram.com");
+        assert_eq!(host("https://instagr.am#top"), "instagr.am");
+    }
+}
@@ -0,0 +1,55 @@
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The `[tls]` section of `config.toml`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// Check Instagram's certificates. Turning this off lets anyone on the
+    /// network read and alter the traffic, cookies included.
+    pub verify: bool,
+    /// PEM bundle yt-dlp trusts instead of its own, e.g. the system bundle
+    /// plus the CA of a TLS-intercepting corporate proxy
+    pub ca_bundle: Option<PathBuf>,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self { verify: true, ca_bundle: None }
+    }
+}
+
+impl TlsConfig {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(bundle) = &self.ca_bundle {
+            if !self.verify {
+                bail!("`ca_bundle` has no effect with `verify = false`");
+            }
+            if !bundle.is_file() {
+                bail!("CA bundle {} does not exist", bundle.display());
+            }
+        }
+        Ok(())
+    }
+
+    /// Shown wherever downloads run while verification is off
+    pub fn warning(&self) -> Option<&'static str> {
+        (!self.verify).then_some("TLS certificate verification is off (`verify = false` in [tls]); traffic can be intercepted")
+    }
+
+    pub fn apply(&self, command: &mut Command) {
+        if !self.verify {
+            command.arg("--no-check-certificates");
+        } else if let Some(bundle) = &self.ca_bundle {
+            // yt-dlp trusts certifi's bundle unless told to use the system
+            // store, which OpenSSL points at SSL_CERT_FILE
+            command
+                .arg("--compat-options")
+                .arg("no-certifi")
+                .env("SSL_CERT_FILE", bundle)
+                .env("REQUESTS_CA_BUNDLE", bundle);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// What instadown has always sent
+const DESKTOP: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36";
+
+/// The Instagram Android app, which some posts are served to more readily
+const MOBILE_APP: &str = "Instagram 309.1.0.41.113 Android (34/14; 420dpi; 1080x2340; samsung; SM-S911B; dm1q; qcom; en_US; 541635890)";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserAgentMode {
+    /// A desktop Chrome
+    #[default]
+    Desktop,
+    /// The Instagram Android app
+    Mobile,
+    /// The strings in `agents`, one after the other
+    Custom,
+}
+
+/// The `[user_agent]` section of `config.toml`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UserAgentConfig {
+    pub mode: UserAgentMode,
+    /// Rotated per job in custom mode
+    pub agents: Vec<String>,
+}
+
+impl UserAgentConfig {
+    pub fn validate(&self) -> Result<()> {
+        match (self.mode, self.agents.is_empty()) {
+            (UserAgentMode::Custom, true) => bail!("`mode = \"custom\"` needs at least one entry in `agents`"),
+            (UserAgentMode::Desktop | UserAgentMode::Mobile, false) => bail!("`agents` are only used with `mode = \"custom\"`"),
+            _ => {}
+        }
+        if self.agents.iter().any(|agent| agent.trim().is_empty()) {
+            bail!("`agents` has an empty user agent");
+        }
+        Ok(())
+    }
+}
+
+/// Hands out the user agent for each request, rotating through the pool
+pub struct UserAgents {
+    agents: Vec<String>,
+    next: AtomicUsize,
+}
+
+impl UserAgents {
+    pub fn new(config: &UserAgentConfig) -> Self {
+        let agents = match config.mode {
+            UserAgentMode::Mobile => vec![MOBILE_APP.to_string()],
+            UserAgentMode::Custom if !config.agents.is_empty() => config.agents.clone(),
+            UserAgentMode::Desktop | UserAgentMode::Custom => vec![DESKTOP.to_string()],
+        };
+        Self { agents, next: AtomicUsize::new(0) }
+    }
+
+    pub fn next(&self) -> String {
+        let index = self.next.fetch_add(1, Ordering::Relaxed);
+        self.agents[index % self.agents.len()].clone()
+    }
+
+    /// The first agent in the pool, without advancing the rotation
+    pub fn first(&self) -> &str {
+        &self.agents[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(mode: UserAgentMode, agents: &[&str]) -> UserAgentConfig {
+        UserAgentConfig {
+            mode,
+            agents: agents.iter().map(|agent| agent.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn validates_agents_against_the_mode() {
+        assert!(config(UserAgentMode::Desktop, &[]).validate().is_ok());
+        assert!(config(UserAgentMode::Mobile, &[]).validate().is_ok());
+        assert!(config(UserAgentMode::Custom, &["a", "b"]).validate().is_ok());
+        assert!(config(UserAgentMode::Custom, &[]).validate().is_err());
+        assert!(config(UserAgentMode::Desktop, &["a"]).validate().is_err());
+        assert!(config(UserAgentMode::Custom, &["a", "  "]).validate().is_err());
+    }
+
+    #[test]
+    fn rotates_through_custom_agents() {
+        let agents = UserAgents::new(&config(UserAgentMode::Custom, &["a", "b", "c"]));
+        let handed_out: Vec<String> = (0..5).map(|_| agents.next()).collect();
+        assert_eq!(handed_out, ["a", "b", "c", "a", "b"]);
+        assert_eq!(agents.first(), "a");
+        assert_eq!(agents.next(), "c");
+    }
+
+    #[test]
+    fn builtin_modes_always_send_the_same_agent() {
+        let desktop = UserAgents::new(&config(UserAgentMode::Desktop, &[]));
+        assert_eq!(desktop.next(), DESKTOP);
+        assert_eq!(desktop.next(), DESKTOP);
+        assert_eq!(UserAgents::new(&config(UserAgentMode::Mobile, &[])).next(), MOBILE_APP);
+    }
+}
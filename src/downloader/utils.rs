@@ -1,29 +1,53 @@
-use anyhow::{Result, Context};
-use std::process::{Command, Child};
-use std::io::{BufRead, BufReader};
+use anyhow::{bail, Result, Context};
+use std::process::Child;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
 use regex::Regex;
 
 use crate::ui::app::DownloadStatus;
+use super::error::{Cancelled, Crashed};
+use super::instagram::MediaInfo;
 
 pub struct DownloadUtils;
 
 impl DownloadUtils {
-    pub fn check_yt_dlp() -> Result<()> {
-        let yt_dlp_check = Command::new("yt-dlp")
-            .arg("--version")
-            .output();
-            
-        if yt_dlp_check.is_err() {
-            return Err(anyhow::anyhow!("yt-dlp is not installed or not in PATH. Please install yt-dlp first."));
+    /// Pulls every Instagram link out of free-form text (e.g. a chat message),
+    /// dropping duplicates while keeping the order they appeared in.
+    pub fn extract_instagram_urls(text: &str) -> Vec<String> {
+        // The host must start the word or follow punctuation, so lookalikes
+        // such as notinstagram.com don't count
+        let url_re = Regex::new(
+            r"(?i)(?:^|[^\w.-])((?:https?://)?(?:www\.|m\.)?(?:instagram\.com|instagr\.am)/[^\s<>\x22'`]+)",
+        )
+        .unwrap();
+
+        let mut urls: Vec<String> = Vec::new();
+        for word in text.split_whitespace() {
+            for found in url_re.captures_iter(word) {
+                // Trailing punctuation usually belongs to the surrounding sentence
+                let url = found[1].trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '}']);
+                let url = if url.starts_with("http") {
+                    url.to_string()
+                } else {
+                    format!("https://{}", url)
+                };
+
+                if !urls.contains(&url) {
+                    urls.push(url);
+                }
+            }
         }
-        Ok(())
+        urls
     }
 
     pub fn handle_download_process(
         mut child: Child,
+        cancel: &AtomicBool,
         mut status_callback: impl FnMut(DownloadStatus),
-        output_template: String,
-    ) -> Result<String> {
+    ) -> Result<Vec<MediaInfo>> {
         let stdout = child.stdout.take().unwrap();
         let stderr = child.stderr.take().unwrap();
         
@@ -32,66 +56,252 @@ impl DownloadUtils {
         let speed_re = Regex::new(r"\[([^\]]+/s)\]").unwrap();
         let eta_re = Regex::new(r"ETA:([^\]]+)\]").unwrap();
         let size_re = Regex::new(r"\[(\d+\.\d+[KMG]iB/\d+\.\d+[KMG]iB)\]").unwrap();
+        let bytes_re = Regex::new(r"\[(\d+)/(\d+)\]").unwrap();
 
-        let mut success = true;
-
-        // Read stdout for progress
-        let stdout_reader = BufReader::new(stdout);
-        for line in stdout_reader.lines() {
-            if let Ok(line) = line {
-                // Parse progress information
-                if line.starts_with("download:") {
-                    let progress = progress_re
-                        .captures(&line)
-                        .and_then(|cap| cap[1].parse::<f32>().ok())
-                        .unwrap_or(0.0);
-
-                    let speed = speed_re
-                        .captures(&line)
-                        .map(|cap| cap[1].to_string())
-                        .unwrap_or_else(|| "0B/s".to_string());
-
-                    let eta = eta_re
-                        .captures(&line)
-                        .map(|cap| cap[1].to_string())
-                        .unwrap_or_else(|| "00:00".to_string());
-
-                    let size = size_re
-                        .captures(&line)
-                        .map(|cap| cap[1].to_string())
-                        .unwrap_or_else(|| "0B/0B".to_string());
-
-                    status_callback(DownloadStatus::Downloading {
-                        progress: progress / 100.0,
-                        speed,
-                        eta,
-                        size,
-                    });
-                }
-            }
-        }
+        // One per entry: a carousel post has several
+        let mut media_info: Vec<MediaInfo> = Vec::new();
+        // Each stream's reported total since the last entry finished; the
+        // count of bytes starting over means yt-dlp moved on to the next stream
+        let mut stream_sizes: Vec<u64> = Vec::new();
+        let mut downloaded = 0;
 
-        // Read stderr for errors
-        let stderr_reader = BufReader::new(stderr);
-        let mut error_message = String::new();
-        for line in stderr_reader.lines() {
-            if let Ok(line) = line {
+        // Drain stderr on its own thread so a chatty yt-dlp can't block on a full pipe
+        let stderr_reader = thread::spawn(move || {
+            let mut error_message = String::new();
+            for line in lossy_lines(stderr) {
                 if !line.trim().is_empty() {
                     error_message.push_str(&line);
                     error_message.push('\n');
-                    success = false;
+                }
+            }
+            error_message
+        });
+
+        // Read stdout on a thread too, so cancellation is noticed even when
+        // yt-dlp goes quiet
+        let (line_tx, line_rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in lossy_lines(stdout) {
+                if line_tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        loop {
+            if cancel.load(Ordering::Relaxed) {
+                kill_group(&mut child);
+                let _ = child.wait();
+                return Err(Cancelled.into());
+            }
+
+            let line = match line_rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            // Parse progress information
+            if line.starts_with("download:") {
+                let mut progress = progress_re
+                    .captures(&line)
+                    .and_then(|cap| cap[1].parse::<f32>().ok())
+                    .unwrap_or(0.0);
+
+                let speed = speed_re
+                    .captures(&line)
+                    .map(|cap| cap[1].to_string())
+                    .unwrap_or_else(|| "0B/s".to_string());
+
+                let eta = eta_re
+                    .captures(&line)
+                    .map(|cap| cap[1].to_string())
+                    .unwrap_or_else(|| "00:00".to_string());
+
+                let size = size_re
+                    .captures(&line)
+                    .map(|cap| cap[1].to_string())
+                    .unwrap_or_else(|| "0B/0B".to_string());
+
+                if let Some(cap) = bytes_re.captures(&line) {
+                    let (now, total): (u64, u64) = (cap[1].parse().unwrap_or(0), cap[2].parse().unwrap_or(0));
+                    match stream_sizes.last_mut() {
+                        Some(last) if now >= downloaded => *last = total,
+                        _ => stream_sizes.push(total),
+                    }
+                    downloaded = now;
+                    // The progress template only reports bytes
+                    if progress == 0.0 && total > 0 {
+                        progress = now as f32 / total as f32 * 100.0;
+                    }
+                }
+
+                status_callback(DownloadStatus::Downloading {
+                    progress: progress / 100.0,
+                    speed,
+                    eta,
+                    size,
+                });
+            } else if line.starts_with('{') {
+                // Final file info from --print after_move, once per entry
+                if let Ok(mut info) = serde_json::from_str::<MediaInfo>(&line) {
+                    info.stream_sizes = std::mem::take(&mut stream_sizes);
+                    downloaded = 0;
+                    media_info.push(info);
                 }
             }
         }
 
         // Wait for the process to complete
         let status = child.wait().context("Failed to wait for yt-dlp")?;
+        let error_message = stderr_reader.join().unwrap_or_default();
 
-        if success && status.success() {
-            Ok(output_template)
+        // Warnings also land on stderr, so only the exit status decides failure
+        if status.success() {
+            if media_info.is_empty() {
+                bail!("yt-dlp did not report the downloaded file");
+            }
+            Ok(media_info)
+        } else if let Some(signal) = killed_by(&status) {
+            Err(Crashed(format!("killed by signal {}", signal)).into())
         } else {
             let error_message = error_message.trim().to_string();
             Err(anyhow::anyhow!("{}", error_message))
         }
     }
-} 
\ No newline at end of file
+}
+
+/// The signal that ended the process, if one did
+fn killed_by(status: &std::process::ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        std::os::unix::process::ExitStatusExt::signal(status)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        None
+    }
+}
+
+/// Kills yt-dlp along with the ffmpeg it may have started. It runs in its
+/// own process group, whose id is its pid.
+fn kill_group(child: &mut Child) {
+    #[cfg(unix)]
+    if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: only signals the group spawned for this download
+        if unsafe { libc::kill(-pid, libc::SIGKILL) } == 0 {
+            return;
+        }
+    }
+    let _ = child.kill();
+}
+
+/// Lines of `reader`, with bytes that aren't UTF-8 replaced instead of
+/// ending the output there, as a caption with bad bytes otherwise would
+fn lossy_lines(reader: impl Read) -> impl Iterator<Item = String> {
+    let mut reader = BufReader::new(reader);
+    std::iter::from_fn(move || {
+        let mut line = Vec::new();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(String::from_utf8_lossy(&line).trim_end_matches(['\n', '\r']).to_string()),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_urls_in_order_without_duplicates() {
+        let text = "look: https://www.instagram.com/p/ABC/, and instagr.am/reel/XYZ) \
+                    then https://www.instagram.com/p/ABC/ again";
+        assert_eq!(
+            DownloadUtils::extract_instagram_urls(text),
+            ["https://www.instagram.com/p/ABC/", "https://instagr.am/reel/XYZ"]
+        );
+    }
+
+    #[test]
+    fn ignores_lookalike_hosts() {
+        let text = "https://notinstagram.com/p/ABC evil.instagram.com/p/DEF my-instagram.com/x";
+        assert!(DownloadUtils::extract_instagram_urls(text).is_empty());
+    }
+
+    #[test]
+    fn finds_urls_after_punctuation() {
+        assert_eq!(
+            DownloadUtils::extract_instagram_urls("(m.instagram.com/p/ABC)"),
+            ["https://m.instagram.com/p/ABC"]
+        );
+    }
+
+    #[test]
+    fn reads_past_invalid_utf8() {
+        let lines: Vec<String> = lossy_lines(&b"one\xff\r\ntwo\n"[..]).collect();
+        assert_eq!(lines, ["one\u{fffd}", "two"]);
+    }
+
+    #[cfg(unix)]
+    fn spawn(script: &str) -> Child {
+        std::process::Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap()
+    }
+
+    #[cfg(unix)]
+    fn run(script: &str) -> Result<Vec<MediaInfo>> {
+        DownloadUtils::handle_download_process(spawn(script), &AtomicBool::new(false), |_| {})
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reports_every_entry_with_its_own_streams() {
+        let entries = run(r#"
+            echo 'download:[5/10][1534023.5594377117][ETA:1]'
+            echo 'download:[10/10][1534023.5594377117][ETA:0]'
+            echo 'download:[3/4][1534023.5594377117][ETA:0]'
+            echo '{"filepath": "a.mp4", "uploader": "bob", "description": null}'
+            echo 'download:[7/7][1534023.5594377117][ETA:0]'
+            echo '{"filepath": "b.jpg", "uploader": "bob", "description": null}'
+        "#)
+        .unwrap();
+        let files: Vec<_> = entries.iter().map(|info| (info.filepath.as_str(), info.stream_sizes.clone())).collect();
+        assert_eq!(files, [("a.mp4", vec![10, 4]), ("b.jpg", vec![7])]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reports_progress_from_bytes() {
+        // What our --progress-template prints, including before yt-dlp knows
+        // the total size or speed
+        let child = spawn(r#"
+            echo 'download:[1048576/NA][NA][ETA:NA]'
+            echo 'download:[2097152/10485760][1534023.5594377117][ETA:5]'
+            echo '{"filepath": "a.mp4"}'
+        "#);
+        let mut progress = Vec::new();
+        DownloadUtils::handle_download_process(child, &AtomicBool::new(false), |status| {
+            if let DownloadStatus::Downloading { progress: fraction, .. } = status {
+                progress.push(fraction);
+            }
+        })
+        .unwrap();
+        assert_eq!(progress, [0.0, 0.2]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn fails_without_a_file_or_on_error() {
+        assert!(run("echo 'download:[1/1][1534023.5594377117][ETA:0]'").is_err());
+        let error = run("echo 'ERROR: Private post' >&2; exit 1").unwrap_err();
+        assert_eq!(error.to_string(), "ERROR: Private post");
+        assert!(run("kill -SEGV $$").unwrap_err().is::<Crashed>());
+    }
+}
@@ -0,0 +1,206 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Extensions that should have a playable duration
+const TIMED_EXTENSIONS: &[&str] = &["mp4", "mkv", "webm", "mov", "m4a"];
+
+/// Merging and remuxing change a download's size by the container overhead,
+/// so only a file smaller than this share of what yt-dlp reported is suspect
+const SIZE_TOLERANCE: f64 = 0.95;
+
+/// The `[verify]` section of `config.toml`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VerifyConfig {
+    /// Check each download before moving it into place; checksums are
+    /// recorded either way
+    pub enabled: bool,
+    /// Probe the container with ffprobe when it is installed
+    pub ffprobe: bool,
+    /// How many times to download a file that fails again before giving up
+    pub redownloads: u32,
+}
+
+impl Default for VerifyConfig {
+    fn default() -> Self {
+        Self { enabled: true, ffprobe: true, redownloads: 1 }
+    }
+}
+
+/// Marker error for a download that stayed broken after every re-download
+#[derive(Debug)]
+pub struct VerificationFailed(pub String);
+
+impl fmt::Display for VerificationFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Verification failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for VerificationFailed {}
+
+/// A download that passed verification
+#[derive(Debug)]
+pub struct Verified {
+    pub sha256: String,
+    /// Something looked off, but not enough to throw the file away
+    pub warning: Option<String>,
+}
+
+/// Checks a finished download and returns its SHA-256, which is all that
+/// is left to do when verification is off. `expected` holds the total
+/// yt-dlp reported for each stream it downloaded.
+///
+/// Only an empty file or one ffprobe can't read fails. A file smaller than
+/// reported is kept with a warning, since yt-dlp's totals are at times
+/// estimates.
+pub fn verify(config: &VerifyConfig, path: &Path, expected: &[u64]) -> Result<Verified> {
+    if !config.enabled {
+        return Ok(Verified { sha256: sha256(path)?, warning: None });
+    }
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("{} is missing", path.display()))?
+        .len();
+    if size == 0 {
+        bail!("the file is empty");
+    }
+    let probed = config.ffprobe && probe(path)?;
+
+    let total: u64 = expected.iter().sum();
+    let warning = ((size as f64) < total as f64 * SIZE_TOLERANCE).then(|| {
+        format!(
+            "the file has {} bytes but yt-dlp reported {}; kept as {}",
+            size,
+            total,
+            if probed { "ffprobe can read it" } else { "there is no ffprobe to check it" }
+        )
+    });
+    Ok(Verified { sha256: sha256(path)?, warning })
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    duration: Option<String>,
+}
+
+/// Asks ffprobe whether the container is readable, has streams and, for
+/// video and audio, a duration. `false` when ffprobe isn't installed.
+fn probe(path: &Path) -> Result<bool> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-show_entries", "format=duration:stream=codec_type", "-of", "json"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .output();
+    let output = match output {
+        Ok(output) => output,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e).context("Failed to start ffprobe"),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("ffprobe can't read it: {}", stderr.lines().last().unwrap_or("no reason given").trim());
+    }
+
+    let probed: ProbeOutput = serde_json::from_slice(&output.stdout).context("ffprobe gave unexpected output")?;
+    if !probed.streams.iter().any(|stream| stream.codec_type.is_some()) {
+        bail!("ffprobe found no audio, video or image stream");
+    }
+    let timed = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| TIMED_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+    let duration = probed
+        .format
+        .and_then(|format| format.duration)
+        .and_then(|duration| duration.parse::<f64>().ok());
+    if timed && !duration.is_some_and(|duration| duration > 0.0) {
+        bail!("ffprobe found no duration");
+    }
+    Ok(true)
+}
+
+fn sha256(path: &Path) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const NO_PROBE: VerifyConfig = VerifyConfig { enabled: true, ffprobe: false, redownloads: 1 };
+
+    fn file(name: &str, len: usize) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("instadown-verify-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, vec![b'x'; len]).unwrap();
+        path
+    }
+
+    #[test]
+    fn checksums_the_file() {
+        let path = file("abc.jpg", 0);
+        std::fs::write(&path, "abc").unwrap();
+        let verified = verify(&NO_PROBE, &path, &[3]).unwrap();
+        assert_eq!(verified.sha256, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert!(verified.warning.is_none());
+    }
+
+    #[test]
+    fn fails_empty_and_missing_files() {
+        assert!(verify(&NO_PROBE, &file("empty.mp4", 0), &[]).is_err());
+        assert!(verify(&NO_PROBE, Path::new("/nonexistent/instadown.mp4"), &[]).is_err());
+    }
+
+    #[test]
+    fn allows_container_overhead() {
+        // A single stream remuxed, and two streams merged, a little smaller
+        assert!(verify(&NO_PROBE, &file("single.mp4", 980), &[1000]).unwrap().warning.is_none());
+        assert!(verify(&NO_PROBE, &file("merged.mp4", 960), &[700, 300]).unwrap().warning.is_none());
+        assert!(verify(&NO_PROBE, &file("bigger.mp4", 1100), &[1000]).unwrap().warning.is_none());
+        assert!(verify(&NO_PROBE, &file("unknown.mp4", 10), &[]).unwrap().warning.is_none());
+    }
+
+    #[test]
+    fn keeps_short_files_with_a_warning() {
+        let verified = verify(&NO_PROBE, &file("short.mp4", 500), &[700, 300]).unwrap();
+        assert_eq!(
+            verified.warning.as_deref(),
+            Some("the file has 500 bytes but yt-dlp reported 1000; kept as there is no ffprobe to check it")
+        );
+    }
+
+    #[test]
+    fn checks_nothing_when_disabled() {
+        let config = VerifyConfig { enabled: false, ..Default::default() };
+        let verified = verify(&config, &file("off.mp4", 1), &[1000]).unwrap();
+        assert!(verified.warning.is_none());
+        assert!(verify(&config, &file("off-empty.mp4", 0), &[]).is_ok());
+    }
+}
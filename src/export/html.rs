@@ -0,0 +1,86 @@
+use anyhow::Result;
+use chrono::Local;
+use std::io::Write;
+
+use crate::ui::app::{AttemptStatus, Download};
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2rem; color: #222; }
+h1 { color: #6a5acd; }
+.summary { display: flex; gap: 1rem; margin-bottom: 1.5rem; }
+.card { background: #f4f1fb; border-radius: 8px; padding: 0.75rem 1.25rem; }
+.card b { display: block; font-size: 1.4rem; }
+table { border-collapse: collapse; width: 100%; font-size: 0.9rem; }
+th, td { text-align: left; padding: 0.4rem 0.6rem; border-bottom: 1px solid #ddd; vertical-align: top; }
+th { background: #9370db; color: white; }
+.completed { color: #2e8b57; }
+.failed { color: #d2381e; }
+.cancelled, .skipped_duplicate { color: #b8860b; }
+.caption { max-width: 30rem; white-space: pre-wrap; }
+";
+
+/// Writes a single HTML file with inline styling, safe to mail around
+pub fn write_report(downloads: &[Download], out: &mut impl Write) -> Result<()> {
+    let completed = downloads.iter().filter(|d| d.status == AttemptStatus::Completed).count();
+    let failed = downloads.iter().filter(|d| matches!(d.status, AttemptStatus::Failed { .. })).count();
+    let total_size: u64 = downloads.iter().filter_map(|d| d.size).sum();
+
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html lang=\"en\"><head><meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>Instadown report</title><style>{}</style></head><body>", STYLE)?;
+    writeln!(out, "<h1>📥 Instadown download report</h1>")?;
+    writeln!(out, "<p>Generated {}</p>", Local::now().format("%Y-%m-%d %H:%M"))?;
+
+    writeln!(out, "<div class=\"summary\">")?;
+    for (label, value) in [
+        ("Attempts", downloads.len().to_string()),
+        ("Completed", completed.to_string()),
+        ("Failed", failed.to_string()),
+        ("Archived", format!("{:.1} MiB", total_size as f64 / (1024.0 * 1024.0))),
+    ] {
+        writeln!(out, "<div class=\"card\"><b>{}</b>{}</div>", value, label)?;
+    }
+    writeln!(out, "</div>")?;
+
+    writeln!(out, "<table><thead><tr>")?;
+    writeln!(out, "<th>Date</th><th>Status</th><th>Uploader</th><th>Post</th><th>File</th><th>Size</th><th>Caption / error</th>")?;
+    writeln!(out, "</tr></thead><tbody>")?;
+
+    for download in downloads {
+        let url = escape(&download.url);
+        // Anything else, such as a javascript: URL, stays plain text
+        let post = if is_web_url(&download.url) {
+            format!("<a href=\"{}\">{}</a>", url, url)
+        } else {
+            url
+        };
+        writeln!(
+            out,
+            "<tr><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td class=\"caption\">{}</td></tr>",
+            download.timestamp.format("%Y-%m-%d %H:%M"),
+            download.status.kind(),
+            escape(&download.status.to_string()),
+            escape(download.uploader.as_deref().unwrap_or("")),
+            post,
+            escape(&download.filename),
+            download.size.map(|s| format!("{:.1} MiB", s as f64 / (1024.0 * 1024.0))).unwrap_or_default(),
+            escape(download.error.as_deref().or(download.caption.as_deref()).unwrap_or("")),
+        )?;
+    }
+
+    writeln!(out, "</tbody></table></body></html>")?;
+    Ok(())
+}
+
+fn is_web_url(url: &str) -> bool {
+    let url = url.trim_start().to_ascii_lowercase();
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
@@ -0,0 +1,142 @@
+pub mod html;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::Write;
+
+use crate::ui::app::{AttemptStatus, Download};
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    #[value(name = "jsonl")]
+    JsonLines,
+    Html,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::JsonLines => "jsonl",
+            Self::Html => "html",
+        }
+    }
+}
+
+/// One history row flattened for reports
+#[derive(Serialize)]
+struct ExportRecord<'a> {
+    timestamp: String,
+    url: &'a str,
+    status: &'static str,
+    error_class: Option<&'static str>,
+    file: &'a str,
+    size_bytes: Option<u64>,
+    duration_ms: u64,
+    uploader: Option<&'a str>,
+    caption: Option<&'a str>,
+    error: Option<&'a str>,
+    user_agent: Option<&'a str>,
+    sha256: Option<&'a str>,
+}
+
+impl<'a> From<&'a Download> for ExportRecord<'a> {
+    fn from(download: &'a Download) -> Self {
+        Self {
+            timestamp: download.timestamp.to_rfc3339(),
+            url: &download.url,
+            status: download.status.kind(),
+            error_class: match &download.status {
+                AttemptStatus::Failed { class } => Some(class.label()),
+                _ => None,
+            },
+            file: &download.filename,
+            size_bytes: download.size,
+            duration_ms: download.duration_ms,
+            uploader: download.uploader.as_deref(),
+            caption: download.caption.as_deref(),
+            error: download.error.as_deref(),
+            user_agent: download.user_agent.as_deref(),
+            sha256: download.sha256.as_deref(),
+        }
+    }
+}
+
+pub fn export(downloads: &[Download], format: ExportFormat, out: &mut impl Write) -> Result<()> {
+    match format {
+        ExportFormat::Csv => write_csv(downloads, out),
+        ExportFormat::JsonLines => write_json_lines(downloads, out),
+        ExportFormat::Html => html::write_report(downloads, out),
+    }
+    .context("Failed to write export")
+}
+
+fn write_csv(downloads: &[Download], out: &mut impl Write) -> Result<()> {
+    writeln!(out, "timestamp,url,status,error_class,file,size_bytes,duration_ms,uploader,caption,error,user_agent,sha256")?;
+
+    for download in downloads {
+        let record = ExportRecord::from(download);
+        let fields = [
+            record.timestamp,
+            record.url.to_string(),
+            record.status.to_string(),
+            record.error_class.unwrap_or_default().to_string(),
+            record.file.to_string(),
+            record.size_bytes.map(|s| s.to_string()).unwrap_or_default(),
+            record.duration_ms.to_string(),
+            record.uploader.unwrap_or_default().to_string(),
+            record.caption.unwrap_or_default().to_string(),
+            record.error.unwrap_or_default().to_string(),
+            record.user_agent.unwrap_or_default().to_string(),
+            record.sha256.unwrap_or_default().to_string(),
+        ];
+        let line: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        writeln!(out, "{}", line.join(","))?;
+    }
+    Ok(())
+}
+
+fn write_json_lines(downloads: &[Download], out: &mut impl Write) -> Result<()> {
+    for download in downloads {
+        serde_json::to_writer(&mut *out, &ExportRecord::from(download))?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Quotes a field per RFC 4180 when it contains separators, quotes or newlines
+fn csv_field(value: &str) -> String {
+    // Spreadsheets run cells starting with these as formulas
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_fields_with_separators() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\"\nbye"), "\"say \"\"hi\"\"\nbye\"");
+    }
+
+    #[test]
+    fn defuses_formulas() {
+        assert_eq!(csv_field("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+        assert_eq!(csv_field("+1"), "'+1");
+        assert_eq!(csv_field("-1"), "'-1");
+        assert_eq!(csv_field("@cmd"), "'@cmd");
+    }
+}
@@ -0,0 +1,303 @@
+mod pages;
+
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::library::ArchivedPost;
+
+const MANIFEST: &str = "manifest.json";
+
+/// Serialises generations so a background update never races a manual one
+static GENERATING: Mutex<()> = Mutex::new(());
+
+/// Static HTML site for browsing the archive offline
+pub struct Gallery {
+    root: PathBuf,
+}
+
+#[derive(Default)]
+pub struct GalleryReport {
+    pub posts: usize,
+    pub written: usize,
+    pub removed: usize,
+}
+
+/// A post together with the file names it gets in the gallery
+struct Entry<'a> {
+    post: &'a ArchivedPost,
+    key: String,
+    fingerprint: u64,
+    thumbnail: Option<String>,
+}
+
+impl Gallery {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Writes pages for new or changed posts, drops pages of posts that are
+    /// gone and refreshes the indexes. `full` rewrites everything.
+    pub fn generate(&self, posts: &[ArchivedPost], full: bool) -> Result<GalleryReport> {
+        let _guard = GENERATING.lock().unwrap_or_else(|e| e.into_inner());
+
+        let posts_dir = self.root.join("posts");
+        let thumbs_dir = self.root.join("thumbs");
+        fs::create_dir_all(&posts_dir)
+            .with_context(|| format!("Failed to create gallery at {}", self.root.display()))?;
+        fs::create_dir_all(&thumbs_dir)?;
+
+        let previous = if full { BTreeMap::new() } else { self.read_manifest() };
+        let ffmpeg = has_ffmpeg();
+        let mut report = GalleryReport {
+            posts: posts.len(),
+            ..GalleryReport::default()
+        };
+
+        let mut used_keys = HashSet::new();
+        let mut entries = Vec::with_capacity(posts.len());
+        for post in posts {
+            let key = match &post.shortcode {
+                Some(code) if used_keys.insert(code.clone()) => code.clone(),
+                _ => format!("post-{}", post.id),
+            };
+            let fingerprint = fingerprint(post);
+            let changed = previous.get(&key) != Some(&fingerprint);
+            let page = posts_dir.join(format!("{}.html", key));
+
+            let thumb_path = thumbs_dir.join(format!("{}.jpg", key));
+            if ffmpeg && (changed || !thumb_path.exists()) {
+                if let Some((media, _)) = post.media.first() {
+                    make_thumbnail(Path::new(media), &thumb_path);
+                }
+            }
+            let thumbnail = if thumb_path.exists() {
+                Some(format!("thumbs/{}.jpg", key))
+            } else {
+                // Without ffmpeg, photos can still stand in for their own thumbnail
+                post.media
+                    .iter()
+                    .map(|(path, _)| Path::new(path))
+                    .find(|path| pages::is_image(path))
+                    .and_then(|path| relative_url(&self.root, path))
+            };
+
+            let entry = Entry { post, key, fingerprint, thumbnail };
+            if changed || !page.exists() {
+                fs::write(&page, pages::post_page(&entry, &posts_dir))
+                    .with_context(|| format!("Failed to write {}", page.display()))?;
+                report.written += 1;
+            }
+            entries.push(entry);
+        }
+
+        for key in previous.keys() {
+            if !entries.iter().any(|entry| &entry.key == key) {
+                let _ = fs::remove_file(posts_dir.join(format!("{}.html", key)));
+                let _ = fs::remove_file(thumbs_dir.join(format!("{}.jpg", key)));
+                report.removed += 1;
+            }
+        }
+
+        fs::write(self.root.join("style.css"), pages::STYLE)?;
+        fs::write(self.root.join("index.html"), pages::uploader_index(&entries))?;
+        fs::write(self.root.join("dates.html"), pages::date_index(&entries))?;
+
+        let manifest: BTreeMap<&str, u64> = entries
+            .iter()
+            .map(|entry| (entry.key.as_str(), entry.fingerprint))
+            .collect();
+        fs::write(self.root.join(MANIFEST), serde_json::to_string_pretty(&manifest)?)?;
+
+        Ok(report)
+    }
+
+    fn read_manifest(&self) -> BTreeMap<String, u64> {
+        fs::read_to_string(self.root.join(MANIFEST))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Regenerates the gallery off the UI thread after each download
+pub struct GalleryUpdater {
+    gallery: Arc<Gallery>,
+    tx: Sender<Result<GalleryReport>>,
+    rx: Receiver<Result<GalleryReport>>,
+}
+
+impl GalleryUpdater {
+    pub fn new(gallery: Gallery) -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self {
+            gallery: Arc::new(gallery),
+            tx,
+            rx,
+        }
+    }
+
+    pub fn schedule(&self, posts: Vec<ArchivedPost>) {
+        let gallery = Arc::clone(&self.gallery);
+        let tx = self.tx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(gallery.generate(&posts, false));
+        });
+    }
+
+    /// Result of the most recently finished update, if any
+    pub fn poll(&self) -> Option<Result<GalleryReport>> {
+        self.rx.try_iter().last()
+    }
+}
+
+/// Changes whenever anything shown on the post's page does. The std hasher
+/// isn't stable across Rust releases, which at worst costs one full rewrite.
+fn fingerprint(post: &ArchivedPost) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    post.url.hash(&mut hasher);
+    post.uploader.hash(&mut hasher);
+    post.caption.hash(&mut hasher);
+    post.timestamp.timestamp().hash(&mut hasher);
+    post.media.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn has_ffmpeg() -> bool {
+    Command::new("ffmpeg")
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Best effort: a missing thumbnail just falls back to a placeholder tile
+fn make_thumbnail(media: &Path, thumb: &Path) {
+    let _ = Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error", "-i"])
+        .arg(media)
+        .args(["-frames:v", "1", "-vf", "thumbnail,scale=320:-2"])
+        .arg(thumb)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
+/// URL of `target` relative to the directory `from`, so the site keeps
+/// working when the download folder is moved or opened over file://
+fn relative_url(from: &Path, target: &Path) -> Option<String> {
+    let from = fs::canonicalize(from).ok()?;
+    let target = fs::canonicalize(target).ok()?;
+    let common = from
+        .components()
+        .zip(target.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return None;
+    }
+
+    let mut parts: Vec<String> = from.components().skip(common).map(|_| "..".to_string()).collect();
+    parts.extend(
+        target
+            .components()
+            .skip(common)
+            .map(|part| percent_encode(&part.as_os_str().to_string_lossy())),
+    );
+    Some(parts.join("/"))
+}
+
+fn percent_encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::Library;
+    use crate::ui::app::{AttemptStatus, Download};
+    use chrono::{Local, TimeZone};
+
+    fn download(url: &str, path: &Path, seconds: i64) -> Download {
+        Download {
+            id: 0,
+            url: url.to_string(),
+            filename: path.to_string_lossy().to_string(),
+            timestamp: Local.timestamp_opt(seconds, 0).unwrap(),
+            status: AttemptStatus::Completed,
+            duration_ms: 0,
+            error: None,
+            uploader: Some("alice".to_string()),
+            caption: Some("Sunset <3 #beach".to_string()),
+            size: Some(1),
+            user_agent: None,
+            removed: false,
+            sha256: None,
+        }
+    }
+
+    #[test]
+    fn renders_library_and_untracked_posts() {
+        let output = std::env::temp_dir().join(format!("instadown-gallery-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&output);
+        fs::create_dir_all(output.join("2024")).unwrap();
+        let video = output.join("Sunset_20240101_ABC.mp4");
+        let photo = output.join("Sunset_20240101_ABC_2.jpg");
+        let untracked = output.join("2024").join("Hand copied_20240102_XYZ.jpg");
+        for path in [&video, &photo, &untracked] {
+            fs::write(path, "x").unwrap();
+        }
+
+        let mut library = Library::open(Path::new(":memory:")).unwrap();
+        let url = "https://www.instagram.com/p/ABC/";
+        library.record_attempt(&download(url, &video, 100)).unwrap();
+        library.record_attempt(&download(url, &photo, 101)).unwrap();
+
+        let gallery = Gallery::new(output.join("gallery"));
+        let posts = library.gallery_posts(&output, &[gallery.root()]).unwrap();
+        let report = gallery.generate(&posts, false).unwrap();
+        assert_eq!((report.posts, report.written, report.removed), (2, 2, 0));
+
+        let index = fs::read_to_string(output.join("gallery/index.html")).unwrap();
+        assert!(index.contains("<h2>@alice <span class=\"meta\">(1)</span></h2>"));
+        assert!(index.contains("<h2>@Unknown uploader"));
+        assert!(index.contains("href=\"posts/ABC.html\""));
+        assert!(index.contains("href=\"posts/XYZ.html\""));
+        if !has_ffmpeg() {
+            assert!(index.contains("src=\"../Sunset_20240101_ABC_2.jpg\""));
+            assert!(index.contains("src=\"../2024/Hand%20copied_20240102_XYZ.jpg\""));
+        }
+
+        let page = fs::read_to_string(output.join("gallery/posts/ABC.html")).unwrap();
+        assert!(page.contains("<video controls preload=\"metadata\" src=\"../../Sunset_20240101_ABC.mp4\">"));
+        assert!(page.contains("<img src=\"../../Sunset_20240101_ABC_2.jpg\""));
+        assert!(page.contains("Sunset &lt;3 #beach"));
+        assert!(page.contains("href=\"https://www.instagram.com/p/ABC/\""));
+        let page = fs::read_to_string(output.join("gallery/posts/XYZ.html")).unwrap();
+        assert!(page.contains("src=\"../../2024/Hand%20copied_20240102_XYZ.jpg\""));
+
+        // Nothing changed, so nothing is rewritten; a vanished post is dropped
+        assert_eq!(gallery.generate(&posts, false).unwrap().written, 0);
+        let report = gallery.generate(&posts[..1], false).unwrap();
+        assert_eq!(report.removed, 1);
+        assert!(!output.join("gallery/posts/XYZ.html").exists());
+    }
+}
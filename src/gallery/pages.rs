@@ -0,0 +1,154 @@
+use chrono::Local;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use super::{relative_url, Entry};
+use crate::export::html::escape;
+
+pub const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 0; color: #222; background: #faf9fd; }
+header { background: #6a5acd; color: white; padding: 1rem 2rem; display: flex; gap: 2rem; align-items: baseline; }
+header h1 { margin: 0; font-size: 1.4rem; }
+header a { color: white; }
+main { padding: 1rem 2rem; }
+h2 { color: #6a5acd; border-bottom: 1px solid #ddd; padding-bottom: 0.25rem; }
+.grid { display: grid; grid-template-columns: repeat(auto-fill, minmax(180px, 1fr)); gap: 1rem; }
+.card { background: white; border-radius: 8px; overflow: hidden; box-shadow: 0 1px 3px #0002; color: inherit; text-decoration: none; }
+.card img, .card .placeholder { width: 100%; aspect-ratio: 1; object-fit: cover; display: block; }
+.placeholder { background: #e6e0f6; color: #6a5acd; font-size: 3rem; display: flex !important; align-items: center; justify-content: center; }
+.card p { margin: 0.5rem; font-size: 0.8rem; overflow: hidden; display: -webkit-box; -webkit-line-clamp: 3; -webkit-box-orient: vertical; }
+.card small { display: block; margin: 0 0.5rem 0.5rem; color: #777; }
+.media video, .media img { max-width: 100%; max-height: 80vh; display: block; margin-bottom: 1rem; }
+.caption { white-space: pre-wrap; max-width: 40rem; }
+.meta { color: #777; }
+";
+
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "webm", "mov"];
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+
+pub fn is_image(path: &Path) -> bool {
+    has_extension(path, IMAGE_EXTENSIONS)
+}
+
+/// Front page: every post grouped by uploader
+pub fn uploader_index(entries: &[Entry]) -> String {
+    let mut groups: BTreeMap<&str, Vec<&Entry>> = BTreeMap::new();
+    for entry in entries {
+        groups
+            .entry(entry.post.uploader.as_deref().unwrap_or("Unknown uploader"))
+            .or_default()
+            .push(entry);
+    }
+
+    let mut body = String::new();
+    for (uploader, posts) in groups {
+        let _ = writeln!(body, "<h2>@{} <span class=\"meta\">({})</span></h2>", escape(uploader), posts.len());
+        write_grid(&mut body, posts.into_iter().rev());
+    }
+    layout("Instadown gallery", "", &format!("{} posts by uploader", entries.len()), &body)
+}
+
+/// Every post grouped by the month it was downloaded, newest first
+pub fn date_index(entries: &[Entry]) -> String {
+    let mut groups: BTreeMap<String, Vec<&Entry>> = BTreeMap::new();
+    for entry in entries {
+        groups
+            .entry(entry.post.timestamp.format("%Y-%m").to_string())
+            .or_default()
+            .push(entry);
+    }
+
+    let mut body = String::new();
+    for posts in groups.into_values().rev() {
+        let month = posts[0].post.timestamp.format("%B %Y");
+        let _ = writeln!(body, "<h2>{} <span class=\"meta\">({})</span></h2>", month, posts.len());
+        write_grid(&mut body, posts.into_iter().rev());
+    }
+    layout("Instadown gallery by date", "", &format!("{} posts by date", entries.len()), &body)
+}
+
+pub fn post_page(entry: &Entry, posts_dir: &Path) -> String {
+    let post = entry.post;
+    let mut body = String::new();
+
+    let _ = writeln!(
+        body,
+        "<p class=\"meta\">@{} · downloaded {} · <a href=\"{}\">original post</a></p>",
+        escape(post.uploader.as_deref().unwrap_or("unknown")),
+        post.timestamp.format("%Y-%m-%d %H:%M"),
+        escape(&post.url),
+    );
+
+    let _ = writeln!(body, "<div class=\"media\">");
+    for (path, _) in &post.media {
+        let path = Path::new(path);
+        let Some(src) = relative_url(posts_dir, path) else {
+            let _ = writeln!(body, "<p class=\"meta\">Missing file: {}</p>", escape(&path.to_string_lossy()));
+            continue;
+        };
+        let src = escape(&src);
+        let _ = if has_extension(path, VIDEO_EXTENSIONS) {
+            writeln!(body, "<video controls preload=\"metadata\" src=\"{}\"></video>", src)
+        } else if is_image(path) {
+            writeln!(body, "<img src=\"{}\" alt=\"\">", src)
+        } else {
+            writeln!(body, "<audio controls src=\"{}\"></audio>", src)
+        };
+    }
+    let _ = writeln!(body, "</div>");
+
+    if let Some(caption) = &post.caption {
+        let _ = writeln!(body, "<p class=\"caption\">{}</p>", escape(caption));
+    }
+
+    let title = post.shortcode.as_deref().unwrap_or(&entry.key);
+    layout(title, "../", title, &body)
+}
+
+fn write_grid<'a>(body: &mut String, entries: impl Iterator<Item = &'a Entry<'a>>) {
+    let _ = writeln!(body, "<div class=\"grid\">");
+    for entry in entries {
+        let thumbnail = match &entry.thumbnail {
+            Some(src) => format!("<img loading=\"lazy\" src=\"{}\" alt=\"\">", escape(src)),
+            None => "<div class=\"placeholder\">▶</div>".to_string(),
+        };
+        let caption: String = entry.post.caption.as_deref().unwrap_or("").chars().take(140).collect();
+        let _ = writeln!(
+            body,
+            "<a class=\"card\" href=\"posts/{}.html\">{}<p>{}</p><small>{}</small></a>",
+            entry.key,
+            thumbnail,
+            escape(&caption),
+            entry.post.timestamp.format("%Y-%m-%d"),
+        );
+    }
+    let _ = writeln!(body, "</div>");
+}
+
+/// Shared page chrome; `base` points back at the gallery root
+fn layout(title: &str, base: &str, heading: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>
+<html lang=\"en\"><head><meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>{title}</title><link rel=\"stylesheet\" href=\"{base}style.css\"></head><body>
+<header><h1>📸 {heading}</h1><a href=\"{base}index.html\">By uploader</a><a href=\"{base}dates.html\">By date</a></header>
+<main>
+{body}</main>
+<footer class=\"meta\"><main>Generated {generated}</main></footer>
+</body></html>
+",
+        title = escape(title),
+        heading = escape(heading),
+        base = base,
+        body = body,
+        generated = Local::now().format("%Y-%m-%d %H:%M"),
+    )
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.contains(&ext.to_lowercase().as_str()))
+}
@@ -0,0 +1,324 @@
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Points in a job's life a hook can fire on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    Queued,
+    Started,
+    Completed,
+    Failed,
+}
+
+impl HookEvent {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Started => "started",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// One `[[hooks]]` entry in `config.toml`: a webhook URL, a command, or both
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookConfig {
+    /// Events to fire on; all of them when left out
+    #[serde(default)]
+    pub events: Vec<HookEvent>,
+    /// POST the payload as JSON to this URL
+    pub url: Option<String>,
+    /// Run this through the shell with the payload in `INSTADOWN_*` variables
+    pub command: Option<String>,
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout() -> u64 {
+    10
+}
+
+/// What a hook is told about the job
+#[derive(Debug, Clone, Serialize)]
+pub struct HookPayload {
+    pub event: HookEvent,
+    pub job_id: u64,
+    pub url: String,
+    pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uploader: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_class: Option<String>,
+}
+
+impl HookPayload {
+    pub fn new(event: HookEvent, job_id: u64, url: &str) -> Self {
+        Self {
+            event,
+            job_id,
+            url: url.to_string(),
+            timestamp: Local::now().to_rfc3339(),
+            file: None,
+            files: Vec::new(),
+            size: None,
+            uploader: None,
+            caption: None,
+            duration_ms: None,
+            error: None,
+            error_class: None,
+        }
+    }
+
+    /// The payload as `INSTADOWN_*` environment variables; unset fields are left out
+    fn env(&self) -> Vec<(&'static str, String)> {
+        let mut vars = vec![
+            ("INSTADOWN_EVENT", self.event.name().to_string()),
+            ("INSTADOWN_JOB_ID", self.job_id.to_string()),
+            ("INSTADOWN_URL", self.url.clone()),
+            ("INSTADOWN_TIMESTAMP", self.timestamp.clone()),
+        ];
+        let optional = [
+            ("INSTADOWN_FILE", self.file.clone()),
+            ("INSTADOWN_FILES", (!self.files.is_empty()).then(|| self.files.join("\n"))),
+            ("INSTADOWN_SIZE", self.size.map(|s| s.to_string())),
+            ("INSTADOWN_UPLOADER", self.uploader.clone()),
+            ("INSTADOWN_CAPTION", self.caption.clone()),
+            ("INSTADOWN_DURATION_MS", self.duration_ms.map(|d| d.to_string())),
+            ("INSTADOWN_ERROR", self.error.clone()),
+            ("INSTADOWN_ERROR_CLASS", self.error_class.clone()),
+        ];
+        vars.extend(optional.into_iter().filter_map(|(name, value)| value.map(|v| (name, v))));
+        vars
+    }
+}
+
+/// Longest quitting waits for hooks that are still queued
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Fires hooks in order on a background thread so a slow endpoint never
+/// holds up downloads. Failures are collected for the owner to report.
+pub struct HookRunner {
+    tx: Option<Sender<HookPayload>>,
+    failures: Option<Receiver<String>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl HookRunner {
+    pub fn new(hooks: &[HookConfig]) -> Self {
+        if hooks.is_empty() {
+            return Self { tx: None, failures: None, worker: None };
+        }
+
+        let hooks = hooks.to_vec();
+        let (tx, rx) = mpsc::channel::<HookPayload>();
+        let (failure_tx, failures) = mpsc::channel();
+        let worker = thread::spawn(move || {
+            for payload in rx {
+                let event = payload.event;
+                for hook in hooks.iter().filter(|h| h.events.is_empty() || h.events.contains(&event)) {
+                    if let Err(e) = fire(hook, &payload) {
+                        let _ = failure_tx.send(format!("{} hook failed: {:#}", event.name(), e));
+                    }
+                }
+            }
+        });
+
+        Self {
+            tx: Some(tx),
+            failures: Some(failures),
+            worker: Some(worker),
+        }
+    }
+
+    pub fn fire(&self, payload: HookPayload) {
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(payload);
+        }
+    }
+
+    pub fn failures(&self) -> Vec<String> {
+        self.failures.iter().flat_map(|rx| rx.try_iter()).collect()
+    }
+}
+
+impl Drop for HookRunner {
+    /// Gives hooks that are already queued `DRAIN_TIMEOUT` to finish, so the
+    /// last job's `completed` hook still fires when a one-shot download
+    /// exits, without a slow endpoint holding up quitting
+    fn drop(&mut self) {
+        self.tx.take();
+        let Some(worker) = self.worker.take() else {
+            return;
+        };
+        let deadline = Instant::now() + DRAIN_TIMEOUT;
+        while !worker.is_finished() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        if worker.is_finished() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn fire(hook: &HookConfig, payload: &HookPayload) -> Result<()> {
+    let timeout = Duration::from_secs(hook.timeout_secs);
+
+    if let Some(url) = &hook.url {
+        ureq::post(url)
+            .timeout(timeout)
+            .set("User-Agent", concat!("instadown/", env!("CARGO_PKG_VERSION")))
+            .send_json(payload)
+            .with_context(|| format!("POST {}", url))?;
+    }
+
+    if let Some(command) = &hook.command {
+        run_command(command, payload, timeout)?;
+    }
+
+    Ok(())
+}
+
+fn run_command(command: &str, payload: &HookPayload, timeout: Duration) -> Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+
+    let mut child = shell
+        .arg(command)
+        .envs(payload.env())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run `{}`", command))?;
+
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if !status.success() {
+                bail!("`{}` exited with {}", command, status);
+            }
+            return Ok(());
+        }
+        if started.elapsed() > timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("`{}` timed out after {}s", command, timeout.as_secs());
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiny_http::{Response, Server};
+
+    fn hook(url: Option<String>, command: Option<&str>, events: Vec<HookEvent>) -> HookConfig {
+        HookConfig { events, url, command: command.map(str::to_string), timeout_secs: 5 }
+    }
+
+    #[test]
+    fn posts_the_payload_to_webhooks() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", server.server_addr().to_ip().unwrap());
+
+        let runner = HookRunner::new(&[hook(Some(url), None, vec![HookEvent::Completed])]);
+        runner.fire(HookPayload::new(HookEvent::Queued, 1, "https://www.instagram.com/p/A/"));
+        let mut payload = HookPayload::new(HookEvent::Completed, 2, "https://www.instagram.com/p/B/");
+        payload.size = Some(42);
+        runner.fire(payload);
+
+        // Only the completed event matches the hook's filter
+        let mut request = server.recv_timeout(Duration::from_secs(5)).unwrap().expect("no webhook call");
+        assert_eq!(request.url(), "/hook");
+        let body: serde_json::Value = serde_json::from_reader(request.as_reader()).unwrap();
+        assert_eq!(body["event"], "completed");
+        assert_eq!(body["job_id"], 2);
+        assert_eq!(body["size"], 42);
+        assert!(body.get("error").is_none());
+        request.respond(Response::empty(204)).unwrap();
+
+        drop(runner);
+        assert!(server.try_recv().unwrap().is_none());
+    }
+
+    #[test]
+    fn reports_failed_webhooks() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", server.server_addr().to_ip().unwrap());
+        let responder = thread::spawn(move || {
+            let request = server.recv().unwrap();
+            request.respond(Response::empty(500)).unwrap();
+        });
+
+        let runner = HookRunner::new(&[hook(Some(url), None, Vec::new())]);
+        runner.fire(HookPayload::new(HookEvent::Failed, 1, "https://www.instagram.com/p/A/"));
+        responder.join().unwrap();
+
+        let started = Instant::now();
+        let mut failures = Vec::new();
+        while failures.is_empty() && started.elapsed() < Duration::from_secs(5) {
+            failures = runner.failures();
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].starts_with("failed hook failed"), "{}", failures[0]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_commands_with_the_payload_in_the_environment() {
+        let payload = HookPayload::new(HookEvent::Started, 7, "https://www.instagram.com/p/A/");
+        assert!(run_command("test \"$INSTADOWN_EVENT/$INSTADOWN_JOB_ID\" = started/7", &payload, Duration::from_secs(5)).is_ok());
+        assert!(run_command("test -n \"$INSTADOWN_FILE\"", &payload, Duration::from_secs(5)).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn kills_commands_that_time_out() {
+        let payload = HookPayload::new(HookEvent::Started, 7, "https://www.instagram.com/p/A/");
+        let started = Instant::now();
+        let error = run_command("sleep 30", &payload, Duration::from_millis(200)).unwrap_err();
+        assert_eq!(error.to_string(), "`sleep 30` timed out after 0s");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn quitting_does_not_wait_for_slow_hooks() {
+        let runner = HookRunner::new(&[hook(None, Some("sleep 5"), Vec::new())]);
+        for id in 0..3 {
+            runner.fire(HookPayload::new(HookEvent::Queued, id, "https://www.instagram.com/p/A/"));
+        }
+        let started = Instant::now();
+        drop(runner);
+        assert!(started.elapsed() < DRAIN_TIMEOUT + Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,183 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{Receiver, Sender};
+
+use crate::core::schedule::TimeWindow;
+use crate::core::Config;
+
+/// One line sent by `instadown download` to the running instance
+#[derive(Serialize, Deserialize)]
+struct ForwardRequest {
+    url: String,
+    #[serde(default)]
+    force: bool,
+    #[serde(default)]
+    window: Option<TimeWindow>,
+}
+
+/// The running instance's answer, also one line
+#[derive(Serialize, Deserialize)]
+pub struct ForwardReply {
+    pub url: String,
+    pub queued: bool,
+}
+
+/// A URL handed over by another invocation, waiting for the owner's answer
+pub struct ForwardedUrl {
+    pub url: String,
+    pub force: bool,
+    pub window: Option<TimeWindow>,
+    reply: Sender<bool>,
+}
+
+impl ForwardedUrl {
+    pub fn respond(self, queued: bool) {
+        let _ = self.reply.send(queued);
+    }
+}
+
+/// Makes this process the single instance that later invocations hand
+/// their URLs to. Removes the socket again when dropped.
+pub struct InstanceListener {
+    rx: Receiver<ForwardedUrl>,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    path: std::path::PathBuf,
+}
+
+impl InstanceListener {
+    /// URLs received since the last call; answer each with `respond`
+    pub fn poll(&self) -> Vec<ForwardedUrl> {
+        self.rx.try_iter().collect()
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use anyhow::{Context, Result};
+    use std::fs;
+    use std::io::{BufRead, BufReader, ErrorKind, Write};
+    use std::os::unix::fs::DirBuilderExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::mpsc::{self, Sender};
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{ForwardReply, ForwardRequest, ForwardedUrl, InstanceListener};
+    use crate::core::schedule::TimeWindow;
+    use crate::core::Config;
+
+    /// How long a forwarding client waits for the instance to answer
+    const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub fn bind(config: &Config) -> Result<Option<InstanceListener>> {
+        let path = config.socket_path();
+        if let Some(parent) = path.parent() {
+            fs::DirBuilder::new().recursive(true).mode(0o700).create(parent)?;
+        }
+
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) if e.kind() == ErrorKind::AddrInUse => {
+                if UnixStream::connect(&path).is_ok() {
+                    return Ok(None);
+                }
+                // Left behind by an instance that crashed
+                fs::remove_file(&path)?;
+                UnixListener::bind(&path)?
+            }
+            Err(e) => return Err(e).with_context(|| format!("Failed to listen on {}", path.display())),
+        };
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().map_while(Result::ok) {
+                let _ = serve(stream, &tx);
+            }
+        });
+
+        Ok(Some(InstanceListener { rx, path }))
+    }
+
+    fn serve(stream: UnixStream, tx: &Sender<ForwardedUrl>) -> Result<()> {
+        stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+        let mut writer = stream.try_clone()?;
+
+        for line in BufReader::new(stream).lines() {
+            let request: ForwardRequest = serde_json::from_str(&line?)?;
+            let (reply_tx, reply_rx) = mpsc::channel();
+            tx.send(ForwardedUrl {
+                url: request.url.clone(),
+                force: request.force,
+                window: request.window,
+                reply: reply_tx,
+            })?;
+
+            let queued = reply_rx.recv_timeout(REPLY_TIMEOUT).unwrap_or(false);
+            let reply = serde_json::to_string(&ForwardReply { url: request.url, queued })?;
+            writeln!(writer, "{}", reply)?;
+        }
+        Ok(())
+    }
+
+    pub fn forward(
+        config: &Config,
+        urls: &[String],
+        force: bool,
+        window: Option<TimeWindow>,
+    ) -> Result<Option<Vec<ForwardReply>>> {
+        let Ok(stream) = UnixStream::connect(config.socket_path()) else {
+            return Ok(None);
+        };
+        stream.set_read_timeout(Some(REPLY_TIMEOUT * 2))?;
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+
+        let mut replies = Vec::with_capacity(urls.len());
+        for url in urls {
+            writeln!(writer, "{}", serde_json::to_string(&ForwardRequest { url: url.clone(), force, window })?)?;
+            let mut line = String::new();
+            reader.read_line(&mut line).context("The running instance did not answer")?;
+            replies.push(serde_json::from_str(&line)?);
+        }
+        Ok(Some(replies))
+    }
+
+    impl Drop for InstanceListener {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Starts listening for forwarded URLs. Returns `None` when another
+/// instance already is, or where Unix sockets aren't available.
+pub fn listen(config: &Config) -> Result<Option<InstanceListener>> {
+    #[cfg(unix)]
+    {
+        unix::bind(config)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = config;
+        Ok(None)
+    }
+}
+
+/// Hands `urls` to the running instance. `None` means nobody is
+/// listening and the caller should do the work itself.
+pub fn forward(
+    config: &Config,
+    urls: &[String],
+    force: bool,
+    window: Option<TimeWindow>,
+) -> Result<Option<Vec<ForwardReply>>> {
+    #[cfg(unix)]
+    {
+        unix::forward(config, urls, force, window)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (config, urls, force, window);
+        Ok(None)
+    }
+}
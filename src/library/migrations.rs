@@ -0,0 +1,111 @@
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+
+/// Schema changes in order; `PRAGMA user_version` records how many have run.
+/// Never edit a shipped migration, append a new one instead.
+const MIGRATIONS: &[&str] = &[
+    // 1: initial library layout
+    "
+    CREATE TABLE uploaders (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE
+    );
+
+    CREATE TABLE posts (
+        id INTEGER PRIMARY KEY,
+        url TEXT NOT NULL UNIQUE,
+        shortcode TEXT,
+        uploader_id INTEGER REFERENCES uploaders(id),
+        caption TEXT
+    );
+
+    CREATE TABLE media_files (
+        id INTEGER PRIMARY KEY,
+        post_id INTEGER NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+        path TEXT NOT NULL UNIQUE,
+        size INTEGER
+    );
+
+    CREATE TABLE tags (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE
+    );
+
+    CREATE TABLE post_tags (
+        post_id INTEGER NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+        tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+        PRIMARY KEY (post_id, tag_id)
+    );
+
+    CREATE TABLE attempts (
+        id INTEGER PRIMARY KEY,
+        post_id INTEGER NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+        media_file_id INTEGER REFERENCES media_files(id) ON DELETE SET NULL,
+        timestamp INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        error_class TEXT,
+        duration_ms INTEGER NOT NULL DEFAULT 0,
+        error TEXT
+    );
+
+    CREATE INDEX attempts_timestamp ON attempts(timestamp);
+    CREATE INDEX attempts_post ON attempts(post_id);
+
+    CREATE VIRTUAL TABLE captions_fts USING fts5(
+        caption,
+        content = 'posts',
+        content_rowid = 'id'
+    );
+
+    CREATE TRIGGER posts_fts_insert AFTER INSERT ON posts BEGIN
+        INSERT INTO captions_fts(rowid, caption) VALUES (new.id, new.caption);
+    END;
+
+    CREATE TRIGGER posts_fts_delete AFTER DELETE ON posts BEGIN
+        INSERT INTO captions_fts(captions_fts, rowid, caption) VALUES ('delete', old.id, old.caption);
+    END;
+
+    CREATE TRIGGER posts_fts_update AFTER UPDATE OF caption ON posts BEGIN
+        INSERT INTO captions_fts(captions_fts, rowid, caption) VALUES ('delete', old.id, old.caption);
+        INSERT INTO captions_fts(rowid, caption) VALUES (new.id, new.caption);
+    END;
+    ",
+    // 2: per-subscription polling state
+    "
+    CREATE TABLE subscriptions (
+        key TEXT PRIMARY KEY,
+        last_checked INTEGER,
+        next_check INTEGER NOT NULL,
+        failures INTEGER NOT NULL DEFAULT 0,
+        last_error TEXT,
+        last_found INTEGER NOT NULL DEFAULT 0
+    );
+    ",
+    // 3: user agent each attempt was made with
+    "
+    ALTER TABLE attempts ADD COLUMN user_agent TEXT;
+    ",
+    // 4: when the storage quota deleted a file
+    "
+    ALTER TABLE media_files ADD COLUMN removed_at INTEGER;
+    ",
+    // 5: checksum of each verified file
+    "
+    ALTER TABLE media_files ADD COLUMN sha256 TEXT;
+    ",
+];
+
+pub fn run(conn: &mut Connection) -> Result<()> {
+    let current: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    for (index, sql) in MIGRATIONS.iter().enumerate().skip(current) {
+        let version = index + 1;
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)
+            .with_context(|| format!("Failed to apply library migration {}", version))?;
+        tx.pragma_update(None, "user_version", version)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,742 @@
+pub mod migrations;
+
+use anyhow::{Context, Result};
+use chrono::{Local, TimeZone};
+use regex::Regex;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::core::history::{HistoryFilter, SortKey};
+use crate::downloader::error::ErrorClass;
+use crate::subscriptions::SubscriptionState;
+use crate::ui::app::{AttemptStatus, Download};
+
+/// File types yt-dlp produces for Instagram posts
+const MEDIA_EXTENSIONS: &[&str] = &["mp4", "mkv", "webm", "mov", "m4a", "jpg", "jpeg", "png", "webp"];
+
+/// SQLite index of every post, media file and download attempt
+pub struct Library {
+    conn: Connection,
+}
+
+/// A post with at least one completed download, as shown in the gallery
+pub struct ArchivedPost {
+    pub id: i64,
+    pub url: String,
+    pub shortcode: Option<String>,
+    pub uploader: Option<String>,
+    pub caption: Option<String>,
+    /// When the newest of its files was downloaded
+    pub timestamp: chrono::DateTime<Local>,
+    /// `(path, size)` of every file, in download order
+    pub media: Vec<(String, Option<u64>)>,
+}
+
+#[derive(Default)]
+pub struct RebuildReport {
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl Library {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create library directory")?;
+        }
+
+        let mut conn = Connection::open(path)
+            .with_context(|| format!("Failed to open library at {}", path.display()))?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        migrations::run(&mut conn)?;
+
+        Ok(Self { conn })
+    }
+
+    /// Stores one download attempt, creating the post, uploader, tags and
+    /// media file rows it refers to. Returns the new attempt id.
+    pub fn record_attempt(&mut self, download: &Download) -> Result<i64> {
+        let tx = self.conn.transaction()?;
+
+        let uploader_id: Option<i64> = match &download.uploader {
+            Some(name) => {
+                tx.execute("INSERT OR IGNORE INTO uploaders (name) VALUES (?1)", [name])?;
+                Some(tx.query_row("SELECT id FROM uploaders WHERE name = ?1", [name], |row| row.get(0))?)
+            }
+            None => None,
+        };
+
+        tx.execute(
+            "INSERT INTO posts (url, shortcode, uploader_id, caption) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(url) DO UPDATE SET
+                 uploader_id = COALESCE(excluded.uploader_id, uploader_id),
+                 caption = COALESCE(excluded.caption, caption)",
+            params![download.url, shortcode(&download.url), uploader_id, download.caption],
+        )?;
+        let post_id: i64 = tx.query_row("SELECT id FROM posts WHERE url = ?1", [&download.url], |row| row.get(0))?;
+
+        if let Some(caption) = &download.caption {
+            for tag in hashtags(caption) {
+                tx.execute("INSERT OR IGNORE INTO tags (name) VALUES (?1)", [&tag])?;
+                tx.execute(
+                    "INSERT OR IGNORE INTO post_tags (post_id, tag_id)
+                     SELECT ?1, id FROM tags WHERE name = ?2",
+                    params![post_id, tag],
+                )?;
+            }
+        }
+
+        let media_file_id: Option<i64> = if download.filename.is_empty() {
+            None
+        } else {
+            tx.execute(
+                "INSERT INTO media_files (post_id, path, size, sha256) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(path) DO UPDATE SET
+                     post_id = excluded.post_id, size = excluded.size, sha256 = excluded.sha256, removed_at = NULL",
+                params![post_id, download.filename, download.size, download.sha256],
+            )?;
+            Some(tx.query_row("SELECT id FROM media_files WHERE path = ?1", [&download.filename], |row| row.get(0))?)
+        };
+
+        let (status, error_class) = status_columns(&download.status);
+        tx.execute(
+            "INSERT INTO attempts (post_id, media_file_id, timestamp, status, error_class, duration_ms, error, user_agent)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                post_id,
+                media_file_id,
+                download.timestamp.timestamp(),
+                status,
+                error_class,
+                download.duration_ms,
+                download.error,
+                download.user_agent,
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+
+        tx.commit()?;
+        Ok(id)
+    }
+
+    /// Attempts matching the History tab's search, filters and sort order
+    pub fn history(&self, filter: &HistoryFilter) -> Result<Vec<Download>> {
+        self.history_window(filter, 0, None)
+    }
+
+    /// Up to `limit` of the attempts `history` would return, skipping the
+    /// first `offset`
+    pub fn history_window(&self, filter: &HistoryFilter, offset: usize, limit: Option<usize>) -> Result<Vec<Download>> {
+        let (conditions, mut args) = history_conditions(filter);
+        let order = match filter.sort {
+            SortKey::Date => "a.timestamp",
+            SortKey::Size => "m.size",
+            SortKey::Uploader => "u.name",
+        };
+        let direction = if filter.ascending { "ASC" } else { "DESC" };
+        let sql = format!(
+            "SELECT a.id, p.url, m.path, a.timestamp, a.status, a.error_class, a.duration_ms,
+                    a.error, u.name, p.caption, m.size, a.user_agent, m.removed_at IS NOT NULL, m.sha256
+             {}{}
+             ORDER BY {} {}, a.id {}
+             LIMIT ? OFFSET ?",
+            HISTORY_FROM, conditions, order, direction, direction
+        );
+        // A negative limit means no limit to SQLite
+        args.push(limit.map_or(-1, |limit| limit as i64).into());
+        args.push((offset as i64).into());
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(args), download_from_row)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>().context("Failed to read history")
+    }
+
+    /// How many attempts `history` would return
+    pub fn history_count(&self, filter: &HistoryFilter) -> Result<usize> {
+        let (conditions, args) = history_conditions(filter);
+        let count: i64 = self
+            .conn
+            .query_row(&format!("SELECT COUNT(*) {}{}", HISTORY_FROM, conditions), params_from_iter(args), |row| row.get(0))
+            .context("Failed to count history")?;
+        Ok(count as usize)
+    }
+
+    /// Posts with a failed or cancelled attempt among those `history` would
+    /// return, most recently tried first
+    pub fn failed_urls(&self, filter: &HistoryFilter) -> Result<Vec<String>> {
+        let (conditions, args) = history_conditions(filter);
+        let sql = format!(
+            "SELECT p.url {}{} AND a.status IN ('failed', 'cancelled')
+             GROUP BY p.url
+             ORDER BY MAX(a.timestamp) DESC",
+            HISTORY_FROM, conditions
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(args), |row| row.get(0))?;
+        rows.collect::<rusqlite::Result<Vec<_>>>().context("Failed to read history")
+    }
+
+    /// Path of the newest completed download of `url`, if any
+    pub fn completed_file(&self, url: &str) -> Result<Option<String>> {
+        let path = self.conn.query_row(
+            "SELECT m.path FROM attempts a
+             JOIN posts p ON p.id = a.post_id
+             JOIN media_files m ON m.id = a.media_file_id
+             WHERE p.url = ?1 AND a.status = 'completed'
+             ORDER BY a.timestamp DESC LIMIT 1",
+            [url],
+            |row| row.get(0),
+        ).optional()?;
+        Ok(path)
+    }
+
+    /// Whether the post behind `url` has ever been seen, matching on the
+    /// shortcode so `/reel/` and `/p/` links to the same post agree
+    pub fn knows_post(&self, url: &str) -> Result<bool> {
+        let found = self.conn.query_row(
+            "SELECT 1 FROM posts WHERE url = ?1 OR shortcode = ?2 LIMIT 1",
+            params![url, shortcode(url)],
+            |_| Ok(()),
+        ).optional()?;
+        Ok(found.is_some())
+    }
+
+    pub fn subscription_states(&self) -> Result<Vec<SubscriptionState>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT key, last_checked, next_check, failures, last_error, last_found FROM subscriptions",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let last_checked: Option<i64> = row.get(1)?;
+            Ok(SubscriptionState {
+                key: row.get(0)?,
+                last_checked: last_checked.and_then(|t| Local.timestamp_opt(t, 0).single()),
+                next_check: Local.timestamp_opt(row.get(2)?, 0).single().unwrap_or_else(Local::now),
+                failures: row.get(3)?,
+                last_error: row.get(4)?,
+                last_found: row.get(5)?,
+            })
+        })?;
+        rows.collect::<rusqlite::Result<_>>().context("Failed to read subscriptions")
+    }
+
+    pub fn save_subscription_state(&self, state: &SubscriptionState) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO subscriptions (key, last_checked, next_check, failures, last_error, last_found)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(key) DO UPDATE SET
+                 last_checked = excluded.last_checked,
+                 next_check = excluded.next_check,
+                 failures = excluded.failures,
+                 last_error = excluded.last_error,
+                 last_found = excluded.last_found",
+            params![
+                state.key,
+                state.last_checked.map(|t| t.timestamp()),
+                state.next_check.timestamp(),
+                state.failures,
+                state.last_error,
+                state.last_found,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Every post that has downloaded files, oldest first
+    pub fn archived_posts(&self) -> Result<Vec<ArchivedPost>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT p.id, p.url, p.shortcode, u.name, p.caption, m.path, m.size, MAX(a.timestamp)
+             FROM media_files m
+             JOIN posts p ON p.id = m.post_id
+             JOIN attempts a ON a.media_file_id = m.id AND a.status = 'completed'
+             LEFT JOIN uploaders u ON u.id = p.uploader_id
+             WHERE m.removed_at IS NULL
+             GROUP BY m.id
+             ORDER BY p.id, MAX(a.timestamp), m.id",
+        )?;
+        let mut rows = stmt.query([])?;
+
+        let mut posts: Vec<ArchivedPost> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let media = (row.get(5)?, row.get(6)?);
+            let timestamp = Local.timestamp_opt(row.get(7)?, 0).single().unwrap_or_else(Local::now);
+
+            match posts.last_mut() {
+                Some(post) if post.id == id => {
+                    post.media.push(media);
+                    post.timestamp = post.timestamp.max(timestamp);
+                }
+                _ => posts.push(ArchivedPost {
+                    id,
+                    url: row.get(1)?,
+                    shortcode: row.get(2)?,
+                    uploader: row.get(3)?,
+                    caption: row.get(4)?,
+                    timestamp,
+                    media: vec![media],
+                }),
+            }
+        }
+
+        posts.sort_by_key(|post| post.timestamp);
+        Ok(posts)
+    }
+
+    /// `archived_posts` plus media in `output_dir` the library has no record
+    /// of, such as files copied in by hand, grouped into posts by URL
+    pub fn gallery_posts(&self, output_dir: &Path, exclude: &[&Path]) -> Result<Vec<ArchivedPost>> {
+        let mut posts = self.archived_posts()?;
+        let known: HashSet<String> = {
+            let mut stmt = self.conn.prepare("SELECT path FROM media_files")?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+
+        let mut files = Vec::new();
+        if output_dir.is_dir() {
+            collect_media_files(output_dir, exclude, &mut files)?;
+        }
+        files.sort();
+
+        let mut by_url: HashMap<String, usize> =
+            posts.iter().enumerate().map(|(index, post)| (post.url.clone(), index)).collect();
+        let mut untracked = 0;
+        for path in files {
+            let path_str = path.to_string_lossy().to_string();
+            if known.contains(&path_str) {
+                continue;
+            }
+            let Ok(metadata) = fs::metadata(&path) else {
+                continue;
+            };
+            let timestamp = metadata
+                .modified()
+                .map(chrono::DateTime::<Local>::from)
+                .unwrap_or_else(|_| Local::now());
+            let info = InfoJson::read_beside(&path);
+            let url = info.webpage_url.unwrap_or_else(|| url_from_filename(&path));
+            let media = (path_str, Some(metadata.len()));
+
+            match by_url.get(&url) {
+                Some(&index) => {
+                    let post = &mut posts[index];
+                    post.media.push(media);
+                    post.timestamp = post.timestamp.max(timestamp);
+                }
+                None => {
+                    // Negative ids can't clash with the library's own
+                    untracked += 1;
+                    by_url.insert(url.clone(), posts.len());
+                    posts.push(ArchivedPost {
+                        id: -untracked,
+                        shortcode: shortcode(&url),
+                        url,
+                        uploader: info.uploader,
+                        caption: info.description,
+                        timestamp,
+                        media: vec![media],
+                    });
+                }
+            }
+        }
+
+        posts.sort_by_key(|post| post.timestamp);
+        Ok(posts)
+    }
+
+    /// `(id, path)` of every downloaded file still kept, the one downloaded
+    /// longest ago first
+    pub fn stored_files(&self) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT m.id, m.path FROM media_files m
+             JOIN attempts a ON a.media_file_id = m.id AND a.status = 'completed'
+             WHERE m.removed_at IS NULL
+             GROUP BY m.id
+             ORDER BY MAX(a.timestamp), m.id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<rusqlite::Result<Vec<_>>>().context("Failed to read media files")
+    }
+
+    /// Ids of the media files at `paths`, skipping any the library doesn't have
+    pub fn media_file_ids(&self, paths: &[String]) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare("SELECT id FROM media_files WHERE path = ?1")?;
+        let mut ids = Vec::new();
+        for path in paths {
+            if let Some(id) = stmt.query_row([path], |row| row.get(0)).optional()? {
+                ids.push(id);
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Whether a media file, even a deleted one, is recorded at `path`
+    pub fn has_file(&self, path: &str) -> Result<bool> {
+        let found = self.conn.query_row("SELECT 1 FROM media_files WHERE path = ?1", [path], |_| Ok(())).optional()?;
+        Ok(found.is_some())
+    }
+
+    /// Points a media file at where it was moved to
+    pub fn file_moved(&mut self, id: i64, path: &str) -> Result<()> {
+        self.conn.execute("UPDATE media_files SET path = ?2 WHERE id = ?1", params![id, path])?;
+        Ok(())
+    }
+
+    /// Marks a media file as deleted to stay within the storage quota
+    pub fn file_removed(&mut self, id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE media_files SET removed_at = ?2 WHERE id = ?1",
+            params![id, Local::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// Whether `url` was downloaded before and its file is still on disk
+    pub fn already_downloaded(&self, url: &str) -> Result<bool> {
+        Ok(self.completed_file(url)?.is_some_and(|path| Path::new(&path).exists()))
+    }
+
+    /// Removes one attempt, dropping its post once no attempts refer to it
+    pub fn delete_attempt(&mut self, id: i64) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM attempts WHERE id = ?1", [id])?;
+        tx.execute("DELETE FROM posts WHERE id NOT IN (SELECT post_id FROM attempts)", [])?;
+        tx.execute(
+            "DELETE FROM media_files WHERE id NOT IN
+                 (SELECT media_file_id FROM attempts WHERE media_file_id IS NOT NULL)",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn vacuum(&self) -> Result<()> {
+        self.conn.execute_batch(
+            "INSERT INTO captions_fts(captions_fts) VALUES ('optimize');
+             VACUUM;",
+        )?;
+        Ok(())
+    }
+
+    /// Runs SQLite's and FTS5's consistency checks, returning any problems
+    pub fn integrity_check(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("PRAGMA integrity_check")?;
+        let mut problems: Vec<String> = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        problems.retain(|p| p != "ok");
+
+        if let Err(e) = self.conn.execute("INSERT INTO captions_fts(captions_fts) VALUES ('integrity-check')", []) {
+            problems.push(format!("caption index: {}", e));
+        }
+
+        Ok(problems)
+    }
+
+    /// Re-syncs the index with the files actually present under `output_dir`:
+    /// forgets media that was deleted and adds files the library never saw.
+    /// Directories in `exclude`, such as the generated gallery, are skipped.
+    pub fn rebuild(&mut self, output_dir: &Path, exclude: &[&Path]) -> Result<RebuildReport> {
+        let mut report = RebuildReport::default();
+
+        let known: Vec<(i64, String)> = {
+            let mut stmt = self.conn.prepare("SELECT id, path FROM media_files")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+
+        for (id, path) in &known {
+            if !Path::new(path).exists() {
+                self.conn.execute("DELETE FROM media_files WHERE id = ?1", [id])?;
+                report.removed += 1;
+            }
+        }
+
+        let known_paths: HashSet<&str> = known.iter().map(|(_, path)| path.as_str()).collect();
+        let mut files = Vec::new();
+        collect_media_files(output_dir, exclude, &mut files)?;
+
+        for path in files {
+            let path_str = path.to_string_lossy().to_string();
+            if known_paths.contains(path_str.as_str()) {
+                continue;
+            }
+
+            let metadata = fs::metadata(&path)?;
+            let timestamp = metadata
+                .modified()
+                .map(chrono::DateTime::<Local>::from)
+                .unwrap_or_else(|_| Local::now());
+            let info = InfoJson::read_beside(&path);
+
+            self.record_attempt(&Download {
+                id: 0,
+                url: info.webpage_url.unwrap_or_else(|| url_from_filename(&path)),
+                filename: path_str,
+                timestamp,
+                status: AttemptStatus::Completed,
+                duration_ms: 0,
+                error: None,
+                uploader: info.uploader,
+                caption: info.description,
+                size: Some(metadata.len()),
+                user_agent: None,
+                removed: false,
+                sha256: None,
+            })?;
+            report.added += 1;
+        }
+
+        Ok(report)
+    }
+}
+
+/// The subset of yt-dlp's `.info.json` sidecar used when rebuilding
+#[derive(Default, serde::Deserialize)]
+struct InfoJson {
+    webpage_url: Option<String>,
+    uploader: Option<String>,
+    description: Option<String>,
+}
+
+impl InfoJson {
+    fn read_beside(media: &Path) -> Self {
+        let sidecar = media.with_extension("info.json");
+        fs::read_to_string(sidecar)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+}
+
+fn collect_media_files(dir: &Path, exclude: &[&Path], files: &mut Vec<std::path::PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with('.'));
+
+        if hidden || exclude.contains(&path.as_path()) {
+            continue;
+        } else if path.is_dir() {
+            collect_media_files(&path, exclude, files)?;
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| MEDIA_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Files are named `title_uploaddate_id.ext`, so the post id is the last segment
+fn url_from_filename(path: &Path) -> String {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    let id = stem.rsplit('_').next().unwrap_or(stem);
+    format!("https://www.instagram.com/p/{}/", id)
+}
+
+fn shortcode(url: &str) -> Option<String> {
+    let re = Regex::new(r"instagram\.com/(?:[^/]+/)?(?:p|reel|reels|tv)/([A-Za-z0-9_-]+)").unwrap();
+    re.captures(url).map(|cap| cap[1].to_string())
+}
+
+fn hashtags(caption: &str) -> Vec<String> {
+    let re = Regex::new(r"#(\w+)").unwrap();
+    let mut tags: Vec<String> = re
+        .captures_iter(caption)
+        .map(|cap| cap[1].to_lowercase())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Tables the History queries read, ready for `AND` conditions
+const HISTORY_FROM: &str = "FROM attempts a
+     JOIN posts p ON p.id = a.post_id
+     LEFT JOIN media_files m ON m.id = a.media_file_id
+     LEFT JOIN uploaders u ON u.id = p.uploader_id
+     WHERE 1 = 1";
+
+/// SQL conditions, appended to `HISTORY_FROM`, and their arguments for a
+/// History filter
+fn history_conditions(filter: &HistoryFilter) -> (String, Vec<rusqlite::types::Value>) {
+    let mut conditions = String::new();
+    let mut args: Vec<rusqlite::types::Value> = Vec::new();
+
+    if let Some(kind) = filter.status.kind() {
+        conditions.push_str(" AND a.status = ?");
+        args.push(kind.to_string().into());
+    }
+    if let Some(since) = filter.date.since() {
+        conditions.push_str(" AND a.timestamp >= ?");
+        args.push(since.timestamp().into());
+    }
+    if let Some(until) = filter.date.until() {
+        conditions.push_str(" AND a.timestamp < ?");
+        args.push(until.timestamp().into());
+    }
+    if let Some(profile) = &filter.profile {
+        conditions.push_str(" AND u.name = ? COLLATE NOCASE");
+        args.push(profile.clone().into());
+    }
+    if !filter.query.is_empty() {
+        conditions.push_str(
+            " AND (p.url LIKE ? ESCAPE '\\' OR m.path LIKE ? ESCAPE '\\'
+                   OR u.name LIKE ? ESCAPE '\\' OR a.error LIKE ? ESCAPE '\\'
+                   OR p.id IN (SELECT rowid FROM captions_fts WHERE captions_fts MATCH ?))",
+        );
+        let like = format!("%{}%", escape_like(&filter.query));
+        args.extend(std::iter::repeat_n(like.into(), 4));
+        args.push(fts_prefix_query(&filter.query).into());
+    }
+    (conditions, args)
+}
+
+/// Makes `%`, `_` and `\` match themselves in a `LIKE ... ESCAPE '\'` pattern
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// Turns free text into an FTS5 prefix query that can't hit syntax errors
+fn fts_prefix_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn status_columns(status: &AttemptStatus) -> (&'static str, Option<String>) {
+    let class = match status {
+        AttemptStatus::Failed { class } => serde_json::to_value(class)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string)),
+        _ => None,
+    };
+    (status.kind(), class)
+}
+
+fn status_from_columns(kind: &str, class: Option<String>) -> AttemptStatus {
+    match kind {
+        "completed" => AttemptStatus::Completed,
+        "cancelled" => AttemptStatus::Cancelled,
+        "skipped_duplicate" => AttemptStatus::SkippedDuplicate,
+        _ => AttemptStatus::Failed {
+            class: class
+                .and_then(|c| serde_json::from_value(serde_json::Value::String(c)).ok())
+                .unwrap_or(ErrorClass::Other),
+        },
+    }
+}
+
+fn download_from_row(row: &Row) -> rusqlite::Result<Download> {
+    let timestamp: i64 = row.get(3)?;
+    let kind: String = row.get(4)?;
+
+    Ok(Download {
+        id: row.get(0)?,
+        url: row.get(1)?,
+        filename: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+        timestamp: Local.timestamp_opt(timestamp, 0).single().unwrap_or_else(Local::now),
+        status: status_from_columns(&kind, row.get(5)?),
+        duration_ms: row.get(6)?,
+        error: row.get(7)?,
+        uploader: row.get(8)?,
+        caption: row.get(9)?,
+        size: row.get(10)?,
+        user_agent: row.get(11)?,
+        removed: row.get::<_, Option<bool>>(12)?.unwrap_or(false),
+        sha256: row.get(13)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attempt(url: &str, uploader: &str, seconds: i64) -> Download {
+        Download {
+            id: 0,
+            url: url.to_string(),
+            filename: format!("/downloads/{}.mp4", seconds),
+            timestamp: Local.timestamp_opt(seconds, 0).unwrap(),
+            status: AttemptStatus::Completed,
+            duration_ms: 0,
+            error: None,
+            uploader: Some(uploader.to_string()),
+            caption: None,
+            size: Some(1),
+            user_agent: None,
+            removed: false,
+            sha256: None,
+        }
+    }
+
+    fn library() -> Library {
+        let mut library = Library::open(Path::new(":memory:")).unwrap();
+        library.record_attempt(&attempt("https://www.instagram.com/p/A_1/", "alice", 100)).unwrap();
+        library.record_attempt(&attempt("https://www.instagram.com/p/AB1/", "Bob", 200)).unwrap();
+        library.record_attempt(&attempt("https://www.instagram.com/p/A%1/", "bob", 300)).unwrap();
+        library
+    }
+
+    fn urls(downloads: Vec<Download>) -> Vec<String> {
+        downloads.into_iter().map(|download| download.url).collect()
+    }
+
+    #[test]
+    fn pages_through_history() {
+        let library = library();
+        let filter = HistoryFilter::default();
+        assert_eq!(library.history_count(&filter).unwrap(), 3);
+        assert_eq!(
+            urls(library.history_window(&filter, 1, Some(1)).unwrap()),
+            ["https://www.instagram.com/p/AB1/"]
+        );
+        assert_eq!(library.history(&filter).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn searches_wildcards_literally() {
+        let library = library();
+        let filter = HistoryFilter { query: "A_1".to_string(), ..HistoryFilter::default() };
+        assert_eq!(urls(library.history(&filter).unwrap()), ["https://www.instagram.com/p/A_1/"]);
+        let filter = HistoryFilter { query: "%".to_string(), ..HistoryFilter::default() };
+        assert_eq!(library.history_count(&filter).unwrap(), 1);
+    }
+
+    #[test]
+    fn lists_each_failed_post_once() {
+        let mut library = library();
+        for (url, status, seconds) in [
+            ("https://www.instagram.com/p/A_1/", AttemptStatus::Failed { class: ErrorClass::Network }, 400),
+            ("https://www.instagram.com/p/A_1/", AttemptStatus::Cancelled, 500),
+            ("https://www.instagram.com/p/AB1/", AttemptStatus::Failed { class: ErrorClass::Private }, 600),
+        ] {
+            let mut download = attempt(url, "alice", seconds);
+            download.status = status;
+            download.filename.clear();
+            library.record_attempt(&download).unwrap();
+        }
+        assert_eq!(
+            library.failed_urls(&HistoryFilter::default()).unwrap(),
+            ["https://www.instagram.com/p/AB1/", "https://www.instagram.com/p/A_1/"]
+        );
+        let filter = HistoryFilter { query: "AB1".to_string(), ..HistoryFilter::default() };
+        assert_eq!(library.failed_urls(&filter).unwrap(), ["https://www.instagram.com/p/AB1/"]);
+    }
+
+    #[test]
+    fn matches_profiles_ignoring_case() {
+        let library = library();
+        let filter = HistoryFilter { profile: Some("BOB".to_string()), ..HistoryFilter::default() };
+        assert_eq!(library.history_count(&filter).unwrap(), 2);
+    }
+}
@@ -1,36 +1,120 @@
-use std::{io, path::PathBuf};
+use std::{io, path::PathBuf, time::Duration};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyCode},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use clap::Parser;
 use ratatui::{
     backend::CrosstermBackend,
     Terminal,
 };
 
 use crate::{
-    ui::{render, app::App},
-    core::Config,
-    downloader::InstagramDownloader,
+    ui::{render, app::{App, QueueBackend}},
+    cli::{Cli, Command},
+    clipboard::ClipboardWatcher,
+    core::{shutdown, Config},
+    daemon::client::RemoteQueue,
+    downloader::{error::QueueInUse, queue::{DownloadQueue, Restored}, InstagramDownloader},
+    library::Library,
+    subscriptions::SubscriptionPoller,
 };
 
+mod cli;
+mod clipboard;
 mod core;
+mod daemon;
+mod diagnostics;
 mod downloader;
+mod export;
+mod gallery;
+mod hooks;
+mod ipc;
+mod library;
+mod native;
+mod subscriptions;
 mod ui;
 
-fn main() -> io::Result<()> {
+fn main() -> anyhow::Result<()> {
+    let loaded = Config::load(PathBuf::from("downloads"));
+
+    // Browsers start the host with their own arguments, which clap can't parse
+    if native::launched_by_browser() {
+        return native::run_host(&loaded?);
+    }
+
+    let cli = Cli::parse();
+    // The doctor reports a broken config.toml rather than stopping at it
+    let config = match (loaded, &cli.command) {
+        (Err(_), Some(Command::Doctor(_))) => Config::with_defaults(PathBuf::from("downloads")),
+        (loaded, _) => loaded?,
+    };
+
+    if let Some(command) = cli.command {
+        return match command {
+            Command::Db { action } => cli::db::run(&config, action),
+            Command::Doctor(args) => cli::doctor::run(&config, args),
+            Command::Download(args) => cli::download::run(&config, args),
+            Command::Export(args) => cli::export::run(&config, args),
+            Command::Gallery(args) => cli::gallery::run(&config, args),
+            Command::NativeHost { action } => cli::native_host::run(&config, action),
+            Command::Serve { port } => daemon::server::run(config, port),
+        };
+    }
+
+    let mut library = Library::open(&config.library_path())?;
+    let mut restored = Restored::default();
+    let mut notification = None;
+    let queue = if cli.attach {
+        QueueBackend::Remote(RemoteQueue::connect(&config)?)
+    } else {
+        let downloader = InstagramDownloader::new(config.clone())?;
+        let mut queue = DownloadQueue::new(downloader);
+        match queue.restore(config.queue_path(), &mut library) {
+            Ok(found) => {
+                restored = found;
+                QueueBackend::Local(Box::new(queue))
+            }
+            // Only one process may run the queue; attach to it if it's a daemon
+            Err(e) if e.is::<QueueInUse>() => match RemoteQueue::connect(&config) {
+                Ok(remote) => {
+                    notification = Some("Attached to the running instadown serve".to_string());
+                    QueueBackend::Remote(remote)
+                }
+                Err(_) => anyhow::bail!("{}; hand it URLs with `instadown download`, or close it first", e),
+            },
+            Err(e) => return Err(e),
+        }
+    };
+    // An attached daemon checks the subscriptions itself
+    let subscriptions = match &queue {
+        QueueBackend::Local(queue) => SubscriptionPoller::from_config(&config, queue.shared_downloader()),
+        QueueBackend::Remote(_) => None,
+    };
+    let clipboard = if cli.watch_clipboard || config.settings.clipboard.enabled {
+        let settings = &config.settings.clipboard;
+        Some(ClipboardWatcher::start(settings.provider()?, Duration::from_millis(settings.interval_ms)))
+    } else {
+        None
+    };
+
+    // Later `instadown download` calls hand their URLs to this instance.
+    // Listen before taking over the terminal so a failure is readable.
+    let listener = ipc::listen(&config)?;
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // Create app state
-    let config = Config::new(PathBuf::from("downloads"));
-    let downloader = InstagramDownloader::new(config).expect("Failed to create downloader");
-    let app = App::new(downloader);
+    let mut app = App::new(config, queue, library, listener, clipboard, subscriptions);
+    app.notification = notification.or_else(|| restored.summary());
+    // Closing the terminal or a kill then goes through the normal exit
+    shutdown::install();
 
     // Start the main loop
     let res = run_app(&mut terminal, app);
@@ -40,7 +124,8 @@ fn main() -> io::Result<()> {
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
 
@@ -52,50 +137,61 @@ fn main() -> io::Result<()> {
 }
 
 fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
-    loop {
+    while !shutdown::requested() {
+        app.tick();
         terminal.draw(|f| render(f, &app))?;
 
-        if let Event::Key(key) = event::read()? {
-            match key.code {
-                KeyCode::Char('q') | KeyCode::Char('Q') => {
-                    if app.focused_area == ui::app::FocusedArea::ExitButton {
+        if !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
+
+        match event::read()? {
+            Event::Key(key) if app.confirm.is_some() => app.handle_confirm_key(key),
+            Event::Key(key) => {
+                let browsing_history = app.selected_tab == 1 && app.input_mode == ui::app::InputMode::Normal;
+                if browsing_history && app.handle_history_key(key) {
+                    continue;
+                }
+
+                match key.code {
+                    KeyCode::Enter if app.input_mode == ui::app::InputMode::Editing => app.submit_url(),
+                    KeyCode::Esc => app.exit_edit_mode(),
+                    KeyCode::Tab => app.toggle_tab(),
+                    _ if app.input_mode == ui::app::InputMode::Editing => app.handle_editing_key(key),
+                    KeyCode::Char('q') | KeyCode::Char('Q') if app.focused_area == ui::app::FocusedArea::ExitButton => {
                         return Ok(());
-                    }
-                },
-                KeyCode::Enter => {
-                    if app.focused_area == ui::app::FocusedArea::ExitButton {
+                    },
+                    KeyCode::Enter => {
+                        if app.focused_area == ui::app::FocusedArea::ExitButton {
+                            return Ok(());
+                        }
+                        app.submit_url();
+                    },
+                    KeyCode::Char('i') => app.enter_edit_mode(),
+                    KeyCode::Char('x') if app.selected_tab == 0 => app.cancel_download(),
+                    KeyCode::Char('r') if app.selected_tab == 3 => app.run_diagnostics(),
+                    KeyCode::Char('e') if app.selected_tab == 3 => app.save_diagnostics(),
+                    KeyCode::Char('c') if app.selected_tab == 3 => app.copy_diagnostics(),
+                    _ => {}
+                }
+            },
+            Event::Paste(text) => app.handle_paste(&text),
+            Event::Mouse(mouse_event) => {
+                use crossterm::event::{MouseButton, MouseEventKind};
+
+                if let MouseEventKind::Down(MouseButton::Left) = mouse_event.kind {
+                    // Get the terminal size
+                    let size = terminal.size()?;
+
+                    // Check if click is in the exit button area (top right)
+                    if mouse_event.row == 1 && mouse_event.column >= size.width - 9 {
+                        app.handle_mouse_click(mouse_event.column, mouse_event.row, ui::app::FocusedArea::ExitButton);
                         return Ok(());
                     }
-                    app.submit_url();
-                },
-                KeyCode::Char('i') => app.enter_edit_mode(),
-                KeyCode::Esc => app.exit_edit_mode(),
-                KeyCode::Tab => app.toggle_tab(),
-                KeyCode::Char(c) => {
-                    if app.input_mode == ui::app::InputMode::Editing {
-                        app.input.push(c);
-                    }
-                },
-                KeyCode::Backspace => {
-                    if app.input_mode == ui::app::InputMode::Editing {
-                        app.input.pop();
-                    }
-                },
-                _ => {}
-            }
-        } else if let Event::Mouse(mouse_event) = event::read()? {
-            use crossterm::event::{MouseButton, MouseEventKind};
-            
-            if let MouseEventKind::Down(MouseButton::Left) = mouse_event.kind {
-                // Get the terminal size
-                let size = terminal.size()?;
-                
-                // Check if click is in the exit button area (top right)
-                if mouse_event.row == 1 && mouse_event.column >= size.width - 9 {
-                    app.handle_mouse_click(mouse_event.column, mouse_event.row, ui::app::FocusedArea::ExitButton);
-                    return Ok(());
                 }
-            }
+            },
+            _ => {}
         }
     }
-} 
\ No newline at end of file
+    Ok(())
+}
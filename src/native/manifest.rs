@@ -0,0 +1,58 @@
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+use super::HOST_NAME;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Browser {
+    Firefox,
+    Chrome,
+    Chromium,
+}
+
+impl Browser {
+    /// The host manifest for this browser's flavour of native messaging
+    pub fn manifest(self, executable: &Path, extension_id: &str) -> Value {
+        let mut manifest = json!({
+            "name": HOST_NAME,
+            "description": "Send Instagram posts to instadown",
+            "path": executable,
+            "type": "stdio",
+        });
+        match self {
+            Self::Firefox => manifest["allowed_extensions"] = json!([extension_id]),
+            Self::Chrome | Self::Chromium => {
+                manifest["allowed_origins"] = json!([format!("chrome-extension://{}/", extension_id)])
+            }
+        }
+        manifest
+    }
+
+    /// Per-user folder the browser looks for host manifests in. Windows
+    /// uses the registry instead, so there is none there.
+    pub fn manifest_dir(self) -> Result<PathBuf> {
+        let home = directories::BaseDirs::new()
+            .map(|dirs| dirs.home_dir().to_path_buf())
+            .context("Cannot find the home directory")?;
+
+        let dir = if cfg!(target_os = "macos") {
+            let support = home.join("Library/Application Support");
+            match self {
+                Self::Firefox => support.join("Mozilla/NativeMessagingHosts"),
+                Self::Chrome => support.join("Google/Chrome/NativeMessagingHosts"),
+                Self::Chromium => support.join("Chromium/NativeMessagingHosts"),
+            }
+        } else if cfg!(unix) {
+            match self {
+                Self::Firefox => home.join(".mozilla/native-messaging-hosts"),
+                Self::Chrome => home.join(".config/google-chrome/NativeMessagingHosts"),
+                Self::Chromium => home.join(".config/chromium/NativeMessagingHosts"),
+            }
+        } else {
+            bail!("Register the manifest under the browser's NativeMessagingHosts registry key instead");
+        };
+        Ok(dir)
+    }
+}
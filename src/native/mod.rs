@@ -0,0 +1,206 @@
+pub mod manifest;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::process::Command;
+
+use crate::core::{platform, Config};
+use crate::downloader::cookies::{self, BrowserCookie};
+use crate::downloader::utils::DownloadUtils;
+use crate::ipc;
+
+/// Name the browser knows the host by; must match the manifest
+pub const HOST_NAME: &str = "instadown";
+
+/// Browsers cap messages to the host at 4 GiB; we never need more than this
+const MAX_MESSAGE_SIZE: u32 = 8 * 1024 * 1024;
+
+/// What the extension sends: a page URL, or text with several, plus
+/// optionally the user's Instagram cookies for private posts
+#[derive(Deserialize)]
+struct HostRequest {
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    urls: Vec<String>,
+    #[serde(default)]
+    cookies: Vec<BrowserCookie>,
+}
+
+#[derive(Serialize, Default)]
+struct HostReply {
+    ok: bool,
+    queued: Vec<String>,
+    skipped: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cookies_saved: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Browsers start the host with the manifest path and extension id
+/// (Firefox) or the caller's origin (Chromium) as arguments
+pub fn launched_by_browser() -> bool {
+    launched_with(&std::env::args().skip(1).collect::<Vec<_>>())
+}
+
+fn launched_with(args: &[String]) -> bool {
+    match args {
+        [origin, ..] if origin.starts_with("chrome-extension://") => true,
+        [manifest, _extension_id] => manifest.ends_with(".json"),
+        _ => false,
+    }
+}
+
+/// Answers messages on stdin until the extension disconnects
+pub fn run_host(config: &Config) -> Result<()> {
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+
+    while let Some(message) = read_message(&mut stdin)? {
+        let reply = match serde_json::from_slice::<HostRequest>(&message) {
+            Ok(request) => handle(config, request).unwrap_or_else(|e| HostReply {
+                error: Some(e.to_string()),
+                ..HostReply::default()
+            }),
+            Err(e) => HostReply {
+                error: Some(format!("Invalid message: {}", e)),
+                ..HostReply::default()
+            },
+        };
+        write_message(&mut stdout, &reply)?;
+    }
+
+    Ok(())
+}
+
+fn handle(config: &Config, request: HostRequest) -> Result<HostReply> {
+    let mut reply = HostReply::default();
+
+    if !request.cookies.is_empty() {
+        reply.cookies_saved = Some(cookies::merge_into(&config.cookies_path(), &request.cookies)?);
+    }
+
+    let text = request.url.into_iter().chain(request.urls).collect::<Vec<_>>().join(" ");
+    let urls = DownloadUtils::extract_instagram_urls(&text);
+    if urls.is_empty() && reply.cookies_saved.is_none() {
+        bail!("No Instagram URL in the message");
+    }
+
+    match ipc::forward(config, &urls, false, None)? {
+        Some(replies) => {
+            for forwarded in replies {
+                if forwarded.queued {
+                    reply.queued.push(forwarded.url);
+                } else {
+                    reply.skipped.push(forwarded.url);
+                }
+            }
+        }
+        None if !urls.is_empty() => {
+            // Nothing running: start a download in the background, which then
+            // takes further URLs itself. The browser may kill us at any time.
+            let exe = std::env::current_exe().context("Cannot find the instadown executable")?;
+            let mut command = Command::new(exe);
+            command.arg("download").args(&urls);
+            #[cfg(unix)]
+            {
+                use std::os::unix::process::CommandExt;
+                command.process_group(0);
+            }
+            platform::spawn_detached(&mut command)?;
+            reply.queued = urls;
+        }
+        None => {}
+    }
+
+    reply.ok = true;
+    Ok(reply)
+}
+
+/// Messages are UTF-8 JSON preceded by their length as a native-endian u32
+fn read_message(input: &mut impl Read) -> Result<Option<Vec<u8>>> {
+    let mut length = [0u8; 4];
+    match input.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+
+    let length = u32::from_ne_bytes(length);
+    if length > MAX_MESSAGE_SIZE {
+        bail!("Message of {} bytes is too large", length);
+    }
+
+    let mut message = vec![0u8; length as usize];
+    input.read_exact(&mut message)?;
+    Ok(Some(message))
+}
+
+fn write_message(output: &mut impl Write, reply: &impl Serialize) -> Result<()> {
+    let body = serde_json::to_vec(reply)?;
+    output.write_all(&(body.len() as u32).to_ne_bytes())?;
+    output.write_all(&body)?;
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn recognises_browser_launches() {
+        // Chromium, Chrome, Edge and Brave pass the caller's origin, on
+        // Windows followed by the parent window handle
+        assert!(launched_with(&args(&["chrome-extension://knldjmfmopnpolahpmmgbagdohdnhkik/"])));
+        assert!(launched_with(&args(&["chrome-extension://knldjmfmopnpolahpmmgbagdohdnhkik/", "--parent-window=6752"])));
+        // Firefox passes the manifest path and the extension id
+        assert!(launched_with(&args(&["/usr/lib/mozilla/native-messaging-hosts/instadown.json", "instadown@example.org"])));
+        assert!(launched_with(&args(&["C:\\Users\\me\\AppData\\Roaming\\instadown\\instadown.json", "instadown@example.org"])));
+
+        assert!(!launched_with(&args(&[])));
+        assert!(!launched_with(&args(&["download", "https://www.instagram.com/p/ABC/"])));
+        assert!(!launched_with(&args(&["/tmp/instadown.json"])));
+    }
+
+    #[test]
+    fn round_trips_messages() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &serde_json::json!({ "ok": true })).unwrap();
+        assert_eq!(&buffer[..4], &11u32.to_ne_bytes());
+
+        let mut input = Cursor::new(buffer);
+        assert_eq!(read_message(&mut input).unwrap().as_deref(), Some(&b"{\"ok\":true}"[..]));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+    }
+
+    #[test]
+    fn ends_cleanly_without_input() {
+        assert_eq!(read_message(&mut Cursor::new(Vec::new())).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_truncated_messages() {
+        let mut input = 10u32.to_ne_bytes().to_vec();
+        input.extend_from_slice(b"{\"url\"");
+        assert!(read_message(&mut Cursor::new(input)).is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_messages() {
+        let input = (MAX_MESSAGE_SIZE + 1).to_ne_bytes().to_vec();
+        let error = read_message(&mut Cursor::new(input)).unwrap_err();
+        assert_eq!(error.to_string(), format!("Message of {} bytes is too large", MAX_MESSAGE_SIZE + 1));
+
+        let mut input = MAX_MESSAGE_SIZE.to_ne_bytes().to_vec();
+        input.resize(4 + MAX_MESSAGE_SIZE as usize, b' ');
+        assert_eq!(read_message(&mut Cursor::new(input)).unwrap().map(|m| m.len()), Some(MAX_MESSAGE_SIZE as usize));
+    }
+}
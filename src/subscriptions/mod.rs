@@ -0,0 +1,340 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local, TimeDelta};
+use serde::Deserialize;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::core::Config;
+use crate::downloader::{error::ErrorClass, InstagramDownloader};
+use crate::library::Library;
+
+/// Longest a subscription is left alone after repeated failures
+const MAX_BACKOFF: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Longest `interval_minutes` a subscription may ask for: a week
+const MAX_INTERVAL_MINUTES: u64 = 7 * 24 * 60;
+
+/// How often the library is asked which subscriptions are due
+const SCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// One `[[subscriptions]]` entry in `config.toml`: a profile or a hashtag
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SubscriptionConfig {
+    pub profile: Option<String>,
+    pub hashtag: Option<String>,
+    /// Minutes between checks
+    #[serde(default = "default_interval")]
+    pub interval_minutes: u64,
+    /// How many of the newest posts to look at per check
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+fn default_interval() -> u64 {
+    60
+}
+
+fn default_limit() -> usize {
+    12
+}
+
+impl SubscriptionConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !matches!((&self.profile, &self.hashtag), (Some(_), None) | (None, Some(_))) {
+            bail!("A subscription needs exactly one of `profile` or `hashtag`");
+        }
+        if self.interval_minutes > MAX_INTERVAL_MINUTES {
+            bail!(
+                "Subscription {}: interval_minutes can be at most {} (a week)",
+                self.label(),
+                MAX_INTERVAL_MINUTES
+            );
+        }
+        Ok(())
+    }
+
+    /// Identifies the subscription's saved state
+    pub fn key(&self) -> String {
+        match (&self.profile, &self.hashtag) {
+            (Some(profile), _) => format!("profile:{}", profile.trim_start_matches('@').to_lowercase()),
+            (None, Some(tag)) => format!("hashtag:{}", tag.trim_start_matches('#').to_lowercase()),
+            (None, None) => String::new(),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match (&self.profile, &self.hashtag) {
+            (Some(profile), _) => format!("@{}", profile.trim_start_matches('@')),
+            (None, Some(tag)) => format!("#{}", tag.trim_start_matches('#')),
+            (None, None) => String::new(),
+        }
+    }
+
+    fn feed_url(&self) -> String {
+        match (&self.profile, &self.hashtag) {
+            (Some(profile), _) => format!("https://www.instagram.com/{}/", profile.trim_start_matches('@')),
+            (None, Some(tag)) => format!("https://www.instagram.com/explore/tags/{}/", tag.trim_start_matches('#')),
+            (None, None) => String::new(),
+        }
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_minutes.max(1).saturating_mul(60))
+    }
+
+    /// How long to wait after `failures` failed checks in a row, the last
+    /// one failing with `message`
+    fn retry_wait(&self, message: &str, failures: u32) -> Duration {
+        // Instagram answers a rate-limited client with more of the same, so
+        // wait exponentially longer; other errors just retry
+        if ErrorClass::classify(message) == ErrorClass::RateLimited {
+            self.interval().saturating_mul(1 << failures.min(10)).min(MAX_BACKOFF)
+        } else {
+            self.interval()
+        }
+    }
+
+    /// Whether the subscription should be checked at `now`. One that was
+    /// never checked is due straight away.
+    fn is_due(&self, states: &[SubscriptionState], now: DateTime<Local>) -> bool {
+        let key = self.key();
+        states
+            .iter()
+            .find(|state| state.key == key)
+            .is_none_or(|state| state.next_check <= now)
+    }
+}
+
+/// `now` plus `wait`, or the furthest backoff if that doesn't fit
+fn after(now: DateTime<Local>, wait: Duration) -> DateTime<Local> {
+    TimeDelta::from_std(wait)
+        .ok()
+        .and_then(|wait| now.checked_add_signed(wait))
+        .unwrap_or_else(|| now + TimeDelta::seconds(MAX_BACKOFF.as_secs() as i64))
+}
+
+/// Saved result of a subscription's checks
+#[derive(Debug, Clone)]
+pub struct SubscriptionState {
+    pub key: String,
+    pub last_checked: Option<DateTime<Local>>,
+    pub next_check: DateTime<Local>,
+    /// Failed checks in a row
+    pub failures: u32,
+    pub last_error: Option<String>,
+    /// New posts found by the last successful check
+    pub last_found: usize,
+}
+
+impl SubscriptionState {
+    fn new(key: String) -> Self {
+        Self {
+            key,
+            last_checked: None,
+            next_check: Local::now(),
+            failures: 0,
+            last_error: None,
+            last_found: 0,
+        }
+    }
+}
+
+/// What a finished check means for the queue
+pub enum SubscriptionEvent {
+    Found { label: String, urls: Vec<String> },
+    Failed { label: String, message: String },
+}
+
+struct CheckResult {
+    subscription: SubscriptionConfig,
+    posts: Result<Vec<String>, String>,
+}
+
+/// Checks due subscriptions one at a time on a background thread. The
+/// owner calls `tick` regularly and queues the posts it returns.
+pub struct SubscriptionPoller {
+    subscriptions: Vec<SubscriptionConfig>,
+    requests: Sender<SubscriptionConfig>,
+    results: Receiver<CheckResult>,
+    checking: Option<String>,
+    last_scan: Option<Instant>,
+}
+
+impl SubscriptionPoller {
+    pub fn new(downloader: Arc<InstagramDownloader>, subscriptions: Vec<SubscriptionConfig>) -> Self {
+        let (requests, rx) = mpsc::channel::<SubscriptionConfig>();
+        let (tx, results) = mpsc::channel();
+
+        thread::spawn(move || {
+            for subscription in rx {
+                let posts = downloader
+                    .list_posts(&subscription.feed_url(), subscription.limit)
+                    .map_err(|e| format!("{:#}", e));
+                if tx.send(CheckResult { subscription, posts }).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            subscriptions,
+            requests,
+            results,
+            checking: None,
+            last_scan: None,
+        }
+    }
+
+    /// A poller for the subscriptions in `config.toml`, if there are any.
+    /// It shares the queue's downloader, so both see the same proxy health
+    /// and user agent rotation.
+    pub fn from_config(config: &Config, downloader: Arc<InstagramDownloader>) -> Option<Self> {
+        if config.settings.subscriptions.is_empty() {
+            return None;
+        }
+        Some(Self::new(downloader, config.settings.subscriptions.clone()))
+    }
+
+    /// Key of the subscription being checked right now
+    pub fn checking(&self) -> Option<&str> {
+        self.checking.as_deref()
+    }
+
+    /// Saves finished checks and starts the next due one
+    pub fn tick(&mut self, library: &mut Library) -> Result<Vec<SubscriptionEvent>> {
+        let mut events = Vec::new();
+        for result in self.results.try_iter().collect::<Vec<_>>() {
+            self.checking = None;
+            events.push(finish(library, result, Local::now())?);
+        }
+
+        let scan_due = self.last_scan.is_none_or(|scan| scan.elapsed() >= SCAN_INTERVAL);
+        if self.checking.is_none() && scan_due {
+            self.last_scan = Some(Instant::now());
+            let states = library.subscription_states()?;
+            let now = Local::now();
+            let due = self.subscriptions.iter().find(|subscription| subscription.is_due(&states, now));
+            if let Some(subscription) = due {
+                self.checking = Some(subscription.key());
+                let _ = self.requests.send(subscription.clone());
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+/// Saves the outcome of a check made at `now`
+fn finish(library: &mut Library, result: CheckResult, now: DateTime<Local>) -> Result<SubscriptionEvent> {
+    let subscription = result.subscription;
+    let key = subscription.key();
+    let mut state = library
+        .subscription_states()?
+        .into_iter()
+        .find(|state| state.key == key)
+        .unwrap_or_else(|| SubscriptionState::new(key));
+    state.last_checked = Some(now);
+
+    let event = match result.posts {
+        Ok(posts) => {
+            let mut urls = Vec::new();
+            for url in posts {
+                if !library.knows_post(&url)? && !urls.contains(&url) {
+                    urls.push(url);
+                }
+            }
+            state.failures = 0;
+            state.last_error = None;
+            state.last_found = urls.len();
+            state.next_check = after(now, subscription.interval());
+            SubscriptionEvent::Found { label: subscription.label(), urls }
+        }
+        Err(message) => {
+            state.failures += 1;
+            state.last_error = Some(message.clone());
+            state.next_check = after(now, subscription.retry_wait(&message, state.failures));
+            SubscriptionEvent::Failed { label: subscription.label(), message }
+        }
+    };
+
+    library.save_subscription_state(&state)?;
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const RATE_LIMITED: &str = "ERROR: [Instagram] natgeo: Unable to download webpage: HTTP Error 429: Too Many Requests";
+
+    fn profile(interval_minutes: u64) -> SubscriptionConfig {
+        SubscriptionConfig {
+            profile: Some("natgeo".to_string()),
+            hashtag: None,
+            interval_minutes,
+            limit: 12,
+        }
+    }
+
+    #[test]
+    fn rejects_intervals_over_a_week() {
+        assert!(profile(MAX_INTERVAL_MINUTES).validate().is_ok());
+        assert!(profile(MAX_INTERVAL_MINUTES + 1).validate().is_err());
+        assert!(profile(u64::MAX).validate().is_err());
+    }
+
+    #[test]
+    fn backs_off_exponentially_only_when_rate_limited() {
+        let subscription = profile(30);
+        let hour = Duration::from_secs(60 * 60);
+        assert_eq!(subscription.retry_wait(RATE_LIMITED, 1), hour);
+        assert_eq!(subscription.retry_wait(RATE_LIMITED, 2), 2 * hour);
+        assert_eq!(subscription.retry_wait(RATE_LIMITED, 3), 4 * hour);
+        assert_eq!(subscription.retry_wait(RATE_LIMITED, 40), MAX_BACKOFF);
+        assert_eq!(subscription.retry_wait("ERROR: Connection reset by peer", 5), subscription.interval());
+        assert_eq!(profile(0).interval(), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn only_checks_subscriptions_that_are_due() {
+        let subscription = profile(60);
+        let now = Local::now();
+        assert!(subscription.is_due(&[], now));
+
+        let mut state = SubscriptionState::new(subscription.key());
+        state.next_check = now + TimeDelta::minutes(5);
+        assert!(!subscription.is_due(std::slice::from_ref(&state), now));
+        assert!(subscription.is_due(&[state], now + TimeDelta::minutes(5)));
+    }
+
+    #[test]
+    fn saves_when_to_check_next() {
+        let mut library = Library::open(Path::new(":memory:")).unwrap();
+        let subscription = profile(30);
+        let now = Local::now();
+        let check = |posts| CheckResult { subscription: subscription.clone(), posts };
+        let saved = |library: &Library| library.subscription_states().unwrap().remove(0);
+
+        let urls = vec!["https://www.instagram.com/p/A/".to_string(); 2];
+        match finish(&mut library, check(Ok(urls)), now).unwrap() {
+            SubscriptionEvent::Found { urls, .. } => assert_eq!(urls.len(), 1),
+            SubscriptionEvent::Failed { .. } => panic!("check should have succeeded"),
+        }
+        assert_eq!(saved(&library).next_check.timestamp(), (now + TimeDelta::minutes(30)).timestamp());
+
+        finish(&mut library, check(Err(RATE_LIMITED.to_string())), now).unwrap();
+        finish(&mut library, check(Err(RATE_LIMITED.to_string())), now).unwrap();
+        let state = saved(&library);
+        assert_eq!(state.failures, 2);
+        assert_eq!(state.next_check.timestamp(), (now + TimeDelta::hours(2)).timestamp());
+
+        finish(&mut library, check(Ok(Vec::new())), now).unwrap();
+        let state = saved(&library);
+        assert_eq!(state.failures, 0);
+        assert_eq!(state.last_error, None);
+    }
+}
@@ -1,7 +1,28 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::path::PathBuf;
-use chrono::Local;
-use crate::downloader::InstagramDownloader;
+use std::time::Duration;
+use chrono::{DateTime, Local};
+use crossterm::event::{KeyCode, KeyEvent};
+use crate::clipboard::{ClipboardAction, ClipboardEvent, ClipboardWatcher};
+use crate::core::{history::{DateRange, HistoryFilter, HistoryPrompt}, platform, schedule::TimeWindow, storage, Config};
+use crate::diagnostics::{DiagnosticsRunner, Report};
+use crate::export::{self, ExportFormat};
+use crate::gallery::{Gallery, GalleryUpdater};
+use crate::ipc::InstanceListener;
+use crate::library::Library;
+use crate::subscriptions::{SubscriptionEvent, SubscriptionPoller, SubscriptionState};
+use crate::daemon::client::RemoteQueue;
+use crate::downloader::{
+    error::ErrorClass,
+    instagram::MediaInfo,
+    queue::{DownloadQueue, Job, JobOutcome, QueueEvent},
+    utils::DownloadUtils,
+};
+use super::editor::LineEditor;
+
+/// History rows kept in memory around the selection
+const HISTORY_WINDOW: usize = 200;
 
 #[derive(Default, PartialEq)]
 pub enum InputMode {
@@ -15,10 +36,22 @@ pub enum FocusedArea {
     #[default]
     Input,
     Tabs,
+    History,
     ExitButton,
 }
 
+/// Action waiting for an answer from the user
+pub enum Confirm {
+    DeleteEntry { id: i64, filename: String, delete_file: bool },
+    Export,
+    /// Links copied to the clipboard, queued on a yes
+    Clipboard(Vec<String>),
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+#[serde(tag = "state", content = "detail", rename_all = "snake_case")]
 pub enum DownloadStatus {
+    #[default]
     None,
     InProgress,
     Downloading {
@@ -31,30 +64,226 @@ pub enum DownloadStatus {
     Error(String),
 }
 
-impl Default for DownloadStatus {
-    fn default() -> Self {
-        Self::None
+/// How a download attempt ended
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AttemptStatus {
+    Completed,
+    Failed { class: ErrorClass },
+    Cancelled,
+    SkippedDuplicate,
+}
+
+impl AttemptStatus {
+    /// Stable name used in the library's `attempts.status` column
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Completed => "completed",
+            Self::Failed { .. } => "failed",
+            Self::Cancelled => "cancelled",
+            Self::SkippedDuplicate => "skipped_duplicate",
+        }
+    }
+}
+
+impl fmt::Display for AttemptStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Completed => write!(f, "Completed"),
+            Self::Failed { class } => write!(f, "Failed ({})", class.label()),
+            Self::Cancelled => write!(f, "Cancelled"),
+            Self::SkippedDuplicate => write!(f, "Skipped (already downloaded)"),
+        }
     }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Download {
+    /// Library row id of this attempt
+    #[serde(default)]
+    pub id: i64,
     pub url: String,
     pub filename: String,
     #[serde(with = "timestamp_seconds")]
     pub timestamp: chrono::DateTime<chrono::Local>,
-    pub status: String,
+    pub status: AttemptStatus,
+    #[serde(default)]
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub uploader: Option<String>,
+    #[serde(default)]
+    pub caption: Option<String>,
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// What the attempt told Instagram it was, to help explain blocks
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// The storage quota deleted the file
+    #[serde(default)]
+    pub removed: bool,
+    /// Hex SHA-256 of the file when it was downloaded
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+impl Download {
+    /// A fresh attempt record stamped with the current time
+    pub fn attempt(url: String, status: AttemptStatus, duration: Duration) -> Self {
+        Self {
+            id: 0,
+            url,
+            filename: String::new(),
+            timestamp: Local::now(),
+            status,
+            duration_ms: duration.as_millis() as u64,
+            error: None,
+            uploader: None,
+            caption: None,
+            size: None,
+            user_agent: None,
+            removed: false,
+            sha256: None,
+        }
+    }
+
+    /// The history entries for a finished queue job: one per file of a
+    /// completed post, else one for the attempt
+    pub fn from_outcome(job: &Job, outcome: &JobOutcome, duration: Duration) -> Vec<Self> {
+        let url = job.url.clone();
+        let mut downloads = match outcome {
+            JobOutcome::Completed { files } => files
+                .iter()
+                .map(|info| {
+                    let mut download = Self::attempt(url.clone(), AttemptStatus::Completed, duration);
+                    download.size = std::fs::metadata(&info.filepath).ok().map(|m| m.len());
+                    download.filename = info.filepath.clone();
+                    download.uploader = info.uploader.clone();
+                    download.caption = info.description.clone();
+                    download.sha256 = info.sha256.clone();
+                    download
+                })
+                .collect(),
+            JobOutcome::Failed { class, message } => {
+                let mut download = Self::attempt(url, AttemptStatus::Failed { class: *class }, duration);
+                download.error = Some(message.clone());
+                vec![download]
+            }
+            JobOutcome::Cancelled => vec![Self::attempt(url, AttemptStatus::Cancelled, duration)],
+        };
+        for download in &mut downloads {
+            download.user_agent = job.user_agent.clone();
+        }
+        downloads
+    }
+}
+
+/// Where the TUI's downloads run: in this process, or in an attached daemon
+pub enum QueueBackend {
+    Local(Box<DownloadQueue>),
+    Remote(RemoteQueue),
+}
+
+impl QueueBackend {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Local(queue) => queue.len(),
+            Self::Remote(queue) => queue.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn current(&self) -> Option<&Job> {
+        match self {
+            Self::Local(queue) => queue.current(),
+            Self::Remote(queue) => queue.current(),
+        }
+    }
+
+    pub fn cancel_current(&mut self) -> bool {
+        match self {
+            Self::Local(queue) => queue.cancel_current(),
+            Self::Remote(queue) => queue.cancel_current(),
+        }
+    }
+
+    /// When the next job may start, if quiet hours or job windows hold it up
+    pub fn waiting_until(&self) -> Option<DateTime<Local>> {
+        match self {
+            Self::Local(queue) => queue.waiting_until(),
+            Self::Remote(queue) => queue.waiting_until(),
+        }
+    }
+
+    /// How long the rate limit holds the next job back. An attached
+    /// daemon applies its limits without reporting them.
+    pub fn throttled_for(&self) -> Option<std::time::Duration> {
+        match self {
+            Self::Local(queue) => queue.throttled_for(),
+            Self::Remote(_) => None,
+        }
+    }
+
+    /// `(free, needed)` bytes while the queue waits for disk space. An
+    /// attached daemon reports it in its own output.
+    pub fn low_on_space(&self) -> Option<(u64, u64)> {
+        match self {
+            Self::Local(queue) => queue.low_on_space(),
+            Self::Remote(_) => None,
+        }
+    }
+
+    /// Usable proxies out of the rotation pool. An attached daemon keeps
+    /// track of its own.
+    pub fn proxy_health(&self) -> Option<(usize, usize)> {
+        match self {
+            Self::Local(queue) => queue.downloader().proxies().health(),
+            Self::Remote(_) => None,
+        }
+    }
+
+    fn poll(&mut self) -> Vec<QueueEvent> {
+        match self {
+            Self::Local(queue) => queue.poll(),
+            Self::Remote(queue) => queue.poll(),
+        }
+    }
 }
 
 pub struct App {
-    pub input: String,
+    pub config: Config,
+    pub input: LineEditor,
     pub input_mode: InputMode,
     pub focused_area: FocusedArea,
     pub selected_tab: usize,
     pub selected_history_item: Option<usize>,
-    pub downloads: Vec<Download>,
+    pub history_filter: HistoryFilter,
+    pub history_prompt: Option<(HistoryPrompt, LineEditor)>,
+    /// The loaded window of the History view, starting at `history_offset`
+    pub history: Vec<Download>,
+    pub history_offset: usize,
+    /// Rows in the whole History view
+    pub history_len: usize,
+    pub library: Library,
+    pub queue: QueueBackend,
+    pub gallery: GalleryUpdater,
+    pub listener: Option<InstanceListener>,
+    pub clipboard: Option<ClipboardWatcher>,
+    /// Checks subscriptions in-process; attached to a daemon, the daemon does
+    pub subscriptions: Option<SubscriptionPoller>,
+    pub subscription_states: Vec<SubscriptionState>,
+    /// Last results shown on the Diagnostics tab
+    pub diagnostics: Option<Report>,
+    diagnostics_runner: Option<DiagnosticsRunner>,
+    /// Copied links waiting for the confirm dialog to be free
+    clipboard_pending: Vec<String>,
+    pub notification: Option<String>,
+    pub confirm: Option<Confirm>,
     pub download_status: DownloadStatus,
-    pub downloader: InstagramDownloader,
 }
 
 mod timestamp_seconds {
@@ -78,23 +307,50 @@ mod timestamp_seconds {
 }
 
 impl App {
-    pub fn new(downloader: InstagramDownloader) -> Self {
+    pub fn new(
+        config: Config,
+        queue: QueueBackend,
+        library: Library,
+        listener: Option<InstanceListener>,
+        clipboard: Option<ClipboardWatcher>,
+        subscriptions: Option<SubscriptionPoller>,
+    ) -> Self {
         // Create downloads directory if it doesn't exist
         let downloads_dir = PathBuf::from("downloads");
         if !downloads_dir.exists() {
             std::fs::create_dir_all(&downloads_dir).expect("Failed to create downloads directory");
         }
 
-        Self {
-            input: String::new(),
+        let gallery = GalleryUpdater::new(Gallery::new(config.gallery_dir()));
+        let mut app = Self {
+            config,
+            input: LineEditor::default(),
             input_mode: InputMode::default(),
             focused_area: FocusedArea::default(),
             selected_tab: 0,
             selected_history_item: None,
-            downloads: Vec::new(),
+            history_filter: HistoryFilter::default(),
+            history_prompt: None,
+            history: Vec::new(),
+            history_offset: 0,
+            history_len: 0,
+            library,
+            queue,
+            gallery,
+            listener,
+            clipboard,
+            clipboard_pending: Vec::new(),
+            subscriptions,
+            subscription_states: Vec::new(),
+            diagnostics: None,
+            diagnostics_runner: None,
+            notification: None,
+            confirm: None,
             download_status: DownloadStatus::default(),
-            downloader,
-        }
+        };
+        app.refresh_history();
+        app.refresh_subscriptions();
+        app
     }
 
     pub fn enter_edit_mode(&mut self) {
@@ -104,60 +360,680 @@ impl App {
 
     pub fn exit_edit_mode(&mut self) {
         self.input_mode = InputMode::Normal;
-        self.focused_area = FocusedArea::Input;
+        self.focused_area = if self.selected_tab == 1 {
+            FocusedArea::History
+        } else {
+            FocusedArea::Input
+        };
     }
 
     pub fn toggle_tab(&mut self) {
-        self.selected_tab = (self.selected_tab + 1) % 2;
+        self.selected_tab = (self.selected_tab + 1) % 4;
         self.focused_area = FocusedArea::Tabs;
+
+        if self.selected_tab == 1 {
+            // Hand the keyboard over to the history list
+            self.input_mode = InputMode::Normal;
+            self.focused_area = FocusedArea::History;
+            if self.selected_history_item.is_none() && self.history_len > 0 {
+                self.selected_history_item = Some(0);
+            }
+        } else if self.selected_tab == 2 {
+            self.input_mode = InputMode::Normal;
+            self.refresh_subscriptions();
+        } else if self.selected_tab == 3 {
+            self.input_mode = InputMode::Normal;
+            self.run_diagnostics();
+        }
+    }
+
+    /// Starts a fresh round of checks for the Diagnostics tab
+    pub fn run_diagnostics(&mut self) {
+        if self.diagnostics_runner.is_none() {
+            self.diagnostics_runner = Some(DiagnosticsRunner::start(self.config.clone()));
+        }
+    }
+
+    /// Whether checks are still running
+    pub fn diagnosing(&self) -> bool {
+        self.diagnostics_runner.is_some()
     }
 
+    /// Writes the redacted diagnostics report to the reports folder
+    pub fn save_diagnostics(&mut self) {
+        let Some(report) = &self.diagnostics else {
+            return;
+        };
+        let reports_dir = self.config.output_dir.join("reports");
+        let path = reports_dir.join(format!("doctor-{}.txt", report.finished.format("%Y%m%d-%H%M%S")));
+
+        let result = std::fs::create_dir_all(&reports_dir).and_then(|_| std::fs::write(&path, report.redacted()));
+        self.notification = Some(match result {
+            Ok(()) => format!("Saved the report to {}", path.display()),
+            Err(e) => format!("Failed to save the report: {}", e),
+        });
+    }
+
+    /// Puts the redacted diagnostics report on the clipboard
+    pub fn copy_diagnostics(&mut self) {
+        let Some(report) = &self.diagnostics else {
+            return;
+        };
+        self.notification = Some(match platform::copy_to_clipboard(&report.redacted()) {
+            Ok(()) => "Copied the report to clipboard".to_string(),
+            Err(e) => format!("Failed to copy the report: {}", e),
+        });
+    }
+
+    /// Queues the typed URL. A trailing `@22:00-06:00` defers it to that window.
     pub fn submit_url(&mut self) {
-        if !self.input.is_empty() {
-            self.download_status = DownloadStatus::InProgress;
-            let url = self.input.clone();
-            
-            match self.downloader.download(&url, |status| {
-                self.download_status = status;
-            }) {
-                Ok(filename) => {
-                    self.add_download(url, filename);
-                    self.input.clear();
-                    self.input_mode = InputMode::Normal;
-                    self.download_status = DownloadStatus::Complete;
-                }
+        let input = self.input.submit().trim().to_string();
+        let (url, window) = match input.rsplit_once(" @") {
+            Some((url, window)) => match window.parse::<TimeWindow>() {
+                Ok(window) => (url.trim().to_string(), Some(window)),
                 Err(e) => {
-                    self.download_status = DownloadStatus::Error(e.to_string());
+                    self.notification = Some(format!("{:#}", e));
+                    return;
                 }
+            },
+            None => (input, None),
+        };
+
+        if url.is_empty() {
+            return;
+        }
+        if !self.enqueue_in(url.clone(), false, window) {
+            self.notification = Some(format!("Skipped {}, already downloaded or queued", url));
+        } else if let Some(window) = window {
+            self.notification = Some(format!("Queued {} to start between {}", url, window));
+        }
+    }
+
+    /// Queues a URL unless, without `force`, it has already been downloaded
+    /// and the file is still on disk. Skips are recorded in history; returns
+    /// whether it was queued.
+    pub fn enqueue(&mut self, url: String, force: bool) -> bool {
+        self.enqueue_in(url, force, None)
+    }
+
+    /// Like `enqueue`, but the job only starts within `window`
+    pub fn enqueue_in(&mut self, url: String, force: bool, window: Option<TimeWindow>) -> bool {
+        let result = match &mut self.queue {
+            QueueBackend::Local(queue) => queue.enqueue_unless_downloaded(&mut self.library, url, force, window),
+            // The daemon does its own duplicate check and records skips
+            QueueBackend::Remote(remote) => remote.enqueue(&url, force, window),
+        };
+
+        match result {
+            Ok(Some(_)) => true,
+            Ok(None) => {
+                self.refresh_history();
+                false
+            }
+            Err(e) => {
+                self.notification = Some(format!("Failed to queue download: {}", e));
+                false
             }
         }
     }
 
-    fn add_download(&mut self, url: String, filename: String) {
-        let download = Download {
-            url,
-            filename,
-            timestamp: Local::now(),
-            status: "Completed".to_string(),
+    pub fn cancel_download(&mut self) {
+        if self.queue.cancel_current() {
+            self.notification = Some("Cancelling download...".to_string());
+        }
+    }
+
+    pub fn handle_editing_key(&mut self, key: KeyEvent) {
+        self.input.handle_key(key);
+    }
+
+    pub fn handle_paste(&mut self, text: &str) {
+        let urls = DownloadUtils::extract_instagram_urls(text);
+
+        if urls.is_empty() {
+            // Nothing recognisable, treat it as regular typing
+            if self.input_mode == InputMode::Editing {
+                self.input.insert_str(text);
+            }
+            return;
+        }
+
+        let count = urls.len();
+        let skipped = urls
+            .into_iter()
+            .filter(|url| !self.enqueue(url.clone(), false))
+            .count();
+        self.input_mode = InputMode::Normal;
+        self.notification = Some(format!(
+            "Detected {} Instagram URL{} in paste, {} added to queue ({} pending{})",
+            count,
+            if count == 1 { "" } else { "s" },
+            count - skipped,
+            self.queue.len(),
+            if skipped > 0 { format!(", {} already downloaded or queued", skipped) } else { String::new() }
+        ));
+    }
+
+    /// Applies progress and results from the download queue
+    pub fn tick(&mut self) {
+        if let Some(Err(e)) = self.gallery.poll() {
+            self.notification = Some(format!("Failed to update gallery: {}", e));
+        }
+
+        let forwarded = self.listener.as_ref().map(|l| l.poll()).unwrap_or_default();
+        if !forwarded.is_empty() {
+            let count = forwarded.len();
+            for request in forwarded {
+                let queued = self.enqueue_in(request.url.clone(), request.force, request.window);
+                request.respond(queued);
+            }
+            self.notification = Some(format!(
+                "Received {} URL{} from another instadown, {} pending",
+                count,
+                if count == 1 { "" } else { "s" },
+                self.queue.len()
+            ));
+        }
+
+        let copied = self.clipboard.as_ref().map(|c| c.poll()).unwrap_or_default();
+        for event in copied {
+            match event {
+                ClipboardEvent::Copied(urls) => self.handle_copied(urls),
+                ClipboardEvent::Error(message) => self.notification = Some(format!("Clipboard: {}", message)),
+            }
+        }
+        self.offer_copied();
+
+        if let Some(report) = self.diagnostics_runner.as_ref().and_then(|runner| runner.poll()) {
+            self.diagnostics = Some(report);
+            self.diagnostics_runner = None;
+        }
+
+        let checked = match &mut self.subscriptions {
+            Some(poller) => poller.tick(&mut self.library),
+            None => Ok(Vec::new()),
+        };
+        match checked {
+            Ok(events) if !events.is_empty() => {
+                for event in events {
+                    self.handle_subscription_event(event);
+                }
+                self.refresh_subscriptions();
+            }
+            Ok(_) => {}
+            Err(e) => self.notification = Some(format!("Failed to check subscriptions: {}", e)),
+        }
+
+        for event in self.queue.poll() {
+            match event {
+                QueueEvent::Started => self.download_status = DownloadStatus::InProgress,
+                QueueEvent::Progress(status) => self.download_status = status,
+                QueueEvent::HookFailed(message) => self.notification = Some(message),
+                QueueEvent::Retrying { job, message } => {
+                    self.download_status = DownloadStatus::None;
+                    self.notification = Some(format!("{}, retrying {}", message, job.url));
+                }
+                QueueEvent::Finished { job, outcome, duration } => {
+                    self.download_status = match &outcome {
+                        JobOutcome::Completed { .. } => DownloadStatus::Complete,
+                        JobOutcome::Failed { message, .. } => DownloadStatus::Error(message.clone()),
+                        JobOutcome::Cancelled => DownloadStatus::Error("Download cancelled".to_string()),
+                    };
+                    let warnings = outcome.warnings();
+                    if !warnings.is_empty() {
+                        self.notification = Some(warnings.join("; "));
+                    }
+
+                    if let QueueBackend::Local(_) = self.queue {
+                        for download in Download::from_outcome(&job, &outcome, duration) {
+                            self.record_attempt(download);
+                        }
+                        if let JobOutcome::Completed { files } = &outcome {
+                            self.enforce_quota(files);
+                            self.update_gallery();
+                        }
+                    } else {
+                        // An attached daemon keeps the library up to date itself
+                        self.refresh_history();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Queues newly copied links, or holds them for confirmation. Links that
+    /// are already in the archive are dropped quietly.
+    fn handle_copied(&mut self, urls: Vec<String>) {
+        let urls: Vec<String> = urls
+            .into_iter()
+            .filter(|url| !self.library.already_downloaded(url).unwrap_or(false))
+            .collect();
+        if urls.is_empty() {
+            return;
+        }
+
+        match self.config.settings.clipboard.action {
+            ClipboardAction::Confirm => self.clipboard_pending.extend(urls),
+            ClipboardAction::Enqueue => {
+                let count = urls.len();
+                let queued = urls.into_iter().filter(|url| self.enqueue(url.clone(), false)).count();
+                self.notification = Some(format!(
+                    "Copied {} Instagram URL{}, {} added to queue ({} pending)",
+                    count,
+                    if count == 1 { "" } else { "s" },
+                    queued,
+                    self.queue.len()
+                ));
+            }
+        }
+    }
+
+    /// Asks about held clipboard links, adding to an open clipboard dialog
+    /// but never replacing a different question
+    fn offer_copied(&mut self) {
+        if self.clipboard_pending.is_empty() {
+            return;
+        }
+        match &mut self.confirm {
+            Some(Confirm::Clipboard(urls)) => {
+                for url in self.clipboard_pending.drain(..) {
+                    if !urls.contains(&url) {
+                        urls.push(url);
+                    }
+                }
+            }
+            Some(_) => {}
+            None => self.confirm = Some(Confirm::Clipboard(std::mem::take(&mut self.clipboard_pending))),
+        }
+    }
+
+    fn handle_subscription_event(&mut self, event: SubscriptionEvent) {
+        match event {
+            SubscriptionEvent::Found { urls, .. } if urls.is_empty() => {}
+            SubscriptionEvent::Found { label, urls } => {
+                let count = urls.len();
+                let queued = urls.into_iter().filter(|url| self.enqueue(url.clone(), false)).count();
+                self.notification = Some(format!(
+                    "{}: {} new post{}, {} added to queue ({} pending)",
+                    label,
+                    count,
+                    if count == 1 { "" } else { "s" },
+                    queued,
+                    self.queue.len()
+                ));
+            }
+            SubscriptionEvent::Failed { label, message } => {
+                self.notification = Some(format!("Failed to check {}: {}", label, message));
+            }
+        }
+    }
+
+    /// Reloads the Subscriptions tab's check times from the library
+    pub fn refresh_subscriptions(&mut self) {
+        match self.library.subscription_states() {
+            Ok(states) => self.subscription_states = states,
+            Err(e) => self.notification = Some(format!("Failed to load subscriptions: {}", e)),
+        }
+    }
+
+    /// Adds newly downloaded posts to the static gallery in the background
+    fn update_gallery(&mut self) {
+        let gallery_dir = self.config.gallery_dir();
+        match self.library.gallery_posts(&self.config.output_dir, &[&gallery_dir]) {
+            Ok(posts) => self.gallery.schedule(posts),
+            Err(e) => self.notification = Some(format!("Failed to update gallery: {}", e)),
+        }
+    }
+
+    fn record_attempt(&mut self, download: Download) {
+        if let Err(e) = self.library.record_attempt(&download) {
+            self.notification = Some(format!("Failed to save history: {}", e));
+        }
+        self.refresh_history();
+    }
+
+    /// Makes room under the storage quota, sparing the `files` just
+    /// downloaded and reporting any files it took
+    fn enforce_quota(&mut self, files: &[MediaInfo]) {
+        let paths: Vec<String> = files.iter().map(|info| info.filepath.clone()).collect();
+        let pruned = self
+            .library
+            .media_file_ids(&paths)
+            .and_then(|keep| storage::enforce_quota(&self.config.settings.storage, &mut self.library, &keep));
+        match pruned {
+            Ok(pruned) => {
+                if let Some(summary) = pruned.summary() {
+                    self.notification = Some(summary);
+                    self.refresh_history();
+                }
+            }
+            Err(e) => self.notification = Some(format!("Storage quota: {:#}", e)),
+        }
+    }
+
+    /// Recounts the History view using the current filter and reloads the
+    /// rows around the selection
+    pub fn refresh_history(&mut self) {
+        match self.library.history_count(&self.history_filter) {
+            Ok(len) => self.history_len = len,
+            Err(e) => self.notification = Some(format!("Failed to load history: {}", e)),
+        }
+
+        self.selected_history_item = match (self.selected_history_item, self.history_len) {
+            (_, 0) => None,
+            (selected, len) => selected.map(|i| i.min(len - 1)),
+        };
+        self.load_history_window();
+    }
+
+    /// Loads the rows centred on the selection
+    fn load_history_window(&mut self) {
+        let selected = self.selected_history_item.unwrap_or(0);
+        let offset = selected.saturating_sub(HISTORY_WINDOW / 2);
+        match self.library.history_window(&self.history_filter, offset, Some(HISTORY_WINDOW)) {
+            Ok(history) => {
+                self.history = history;
+                self.history_offset = offset;
+            }
+            Err(e) => self.notification = Some(format!("Failed to load history: {}", e)),
+        }
+    }
+
+    pub fn selected_download(&self) -> Option<&Download> {
+        self.selected_history_item
+            .and_then(|i| i.checked_sub(self.history_offset))
+            .and_then(|i| self.history.get(i))
+    }
+
+    /// Handles navigation and actions in the History tab. Returns false when
+    /// the key isn't a history binding so the caller can fall back to globals.
+    pub fn handle_history_key(&mut self, key: KeyEvent) -> bool {
+        if self.history_prompt.is_some() {
+            self.handle_prompt_key(key);
+            return true;
+        }
+
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.move_history_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_history_selection(1),
+            KeyCode::PageUp => self.move_history_selection(-10),
+            KeyCode::PageDown => self.move_history_selection(10),
+            KeyCode::Home | KeyCode::Char('g') => self.move_history_selection(isize::MIN),
+            KeyCode::End | KeyCode::Char('G') => self.move_history_selection(isize::MAX),
+            KeyCode::Enter | KeyCode::Char('r') => self.redownload_selected(),
+            KeyCode::Char('R') => self.retry_failed(),
+            KeyCode::Char('c') => self.copy_selected_url(),
+            KeyCode::Char('o') => self.open_selected(false),
+            KeyCode::Char('f') => self.open_selected(true),
+            KeyCode::Char('d') => self.request_delete(false),
+            KeyCode::Char('D') => self.request_delete(true),
+            KeyCode::Char('/') => self.open_history_prompt(HistoryPrompt::Search),
+            KeyCode::Char('t') => self.update_filter(|f| f.status = f.status.next()),
+            KeyCode::Char('w') => self.update_filter(|f| f.date = f.date.next()),
+            KeyCode::Char('W') => self.open_history_prompt(HistoryPrompt::Dates),
+            KeyCode::Char('s') => self.update_filter(|f| f.sort = f.sort.next()),
+            KeyCode::Char('S') => self.update_filter(|f| f.ascending = !f.ascending),
+            KeyCode::Char('p') => self.toggle_profile_filter(),
+            KeyCode::Char('P') => self.open_history_prompt(HistoryPrompt::Profile),
+            KeyCode::Char('x') => self.update_filter(|f| *f = HistoryFilter::default()),
+            KeyCode::Char('e') => self.confirm = Some(Confirm::Export),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Opens the prompt with the filter's current value ready to edit
+    fn open_history_prompt(&mut self, prompt: HistoryPrompt) {
+        let current = match prompt {
+            HistoryPrompt::Search => String::new(),
+            HistoryPrompt::Profile => self
+                .history_filter
+                .profile
+                .clone()
+                .or_else(|| self.selected_download().and_then(|d| d.uploader.clone()))
+                .unwrap_or_default(),
+            HistoryPrompt::Dates => self.history_filter.date.as_input(),
+        };
+        let mut editor = LineEditor::default();
+        editor.insert_str(&current);
+        self.history_prompt = Some((prompt, editor));
+    }
+
+    /// Search narrows the list as the query is typed; the profile and date
+    /// prompts apply on Enter
+    fn handle_prompt_key(&mut self, key: KeyEvent) {
+        let Some((prompt, editor)) = self.history_prompt.as_mut() else {
+            return;
+        };
+        let prompt = *prompt;
+
+        match (prompt, key.code) {
+            (HistoryPrompt::Search, KeyCode::Enter) => self.history_prompt = None,
+            (HistoryPrompt::Search, KeyCode::Esc) => {
+                self.history_prompt = None;
+                self.update_filter(|f| f.query.clear());
+            }
+            (HistoryPrompt::Search, _) => {
+                editor.handle_key(key);
+                let query = editor.as_str().to_string();
+                self.update_filter(|f| f.query = query);
+            }
+            (_, KeyCode::Esc) => self.history_prompt = None,
+            (HistoryPrompt::Profile, KeyCode::Enter) => {
+                let profile = editor.as_str().trim().trim_start_matches('@').to_string();
+                self.history_prompt = None;
+                self.update_filter(|f| f.profile = (!profile.is_empty()).then_some(profile));
+            }
+            (HistoryPrompt::Dates, KeyCode::Enter) => match DateRange::parse(editor.as_str()) {
+                Ok(date) => {
+                    self.history_prompt = None;
+                    self.update_filter(|f| f.date = date);
+                }
+                // Keep the prompt open so the range can be corrected
+                Err(e) => self.notification = Some(format!("{:#}", e)),
+            },
+            _ => editor.handle_key(key),
+        }
+    }
+
+    fn update_filter(&mut self, change: impl FnOnce(&mut HistoryFilter)) {
+        change(&mut self.history_filter);
+        self.selected_history_item = Some(0);
+        self.refresh_history();
+    }
+
+    fn toggle_profile_filter(&mut self) {
+        if self.history_filter.profile.is_some() {
+            self.update_filter(|f| f.profile = None);
+        } else if let Some(uploader) = self.selected_download().and_then(|d| d.uploader.clone()) {
+            self.update_filter(|f| f.profile = Some(uploader));
+        }
+    }
+
+    pub fn handle_confirm_key(&mut self, key: KeyEvent) {
+        let Some(confirm) = self.confirm.take() else {
+            return;
+        };
+
+        match (confirm, key.code) {
+            (Confirm::DeleteEntry { id, filename, delete_file }, KeyCode::Char('y') | KeyCode::Char('Y')) => {
+                self.delete_entry(id, &filename, delete_file)
+            }
+            (Confirm::Export, KeyCode::Char('c')) => self.export_history(ExportFormat::Csv),
+            (Confirm::Export, KeyCode::Char('j')) => self.export_history(ExportFormat::JsonLines),
+            (Confirm::Export, KeyCode::Char('h')) => self.export_history(ExportFormat::Html),
+            (Confirm::Clipboard(urls), KeyCode::Char('y') | KeyCode::Char('Y')) => {
+                let count = urls.len();
+                let queued = urls.into_iter().filter(|url| self.enqueue(url.clone(), false)).count();
+                self.notification = Some(format!(
+                    "{} of {} copied URL{} added to queue ({} pending)",
+                    queued,
+                    count,
+                    if count == 1 { "" } else { "s" },
+                    self.queue.len()
+                ));
+            }
+            _ => self.notification = Some("Cancelled".to_string()),
+        }
+    }
+
+    /// Writes the current History view to the reports folder
+    fn export_history(&mut self, format: ExportFormat) {
+        let downloads = match self.library.history(&self.history_filter) {
+            Ok(downloads) => downloads,
+            Err(e) => {
+                self.notification = Some(format!("Export failed: {}", e));
+                return;
+            }
+        };
+        let reports_dir = self.config.output_dir.join("reports");
+        let path = reports_dir.join(format!(
+            "history-{}.{}",
+            Local::now().format("%Y%m%d-%H%M%S"),
+            format.extension()
+        ));
+
+        let result = std::fs::create_dir_all(&reports_dir)
+            .and_then(|_| std::fs::File::create(&path))
+            .map_err(anyhow::Error::from)
+            .and_then(|file| export::export(&downloads, format, &mut std::io::BufWriter::new(file)));
+
+        self.notification = Some(match result {
+            Ok(()) => format!("Exported {} entries to {}", downloads.len(), path.display()),
+            Err(e) => format!("Export failed: {}", e),
+        });
+    }
+
+    fn move_history_selection(&mut self, delta: isize) {
+        if self.history_len == 0 {
+            self.selected_history_item = None;
+            return;
+        }
+
+        let last = self.history_len - 1;
+        let current = self.selected_history_item.unwrap_or(0);
+        let next = if delta < 0 {
+            current.saturating_sub(delta.unsigned_abs())
+        } else {
+            current.saturating_add(delta as usize).min(last)
+        };
+        self.selected_history_item = Some(next);
+        if !(self.history_offset..self.history_offset + self.history.len()).contains(&next) {
+            self.load_history_window();
+        }
+    }
+
+    fn redownload_selected(&mut self) {
+        let Some(download) = self.selected_download() else {
+            return;
         };
-        self.downloads.push(download);
+
+        let url = download.url.clone();
+        self.notification = Some(match download.status {
+            AttemptStatus::Completed | AttemptStatus::SkippedDuplicate => format!("Queued {} again", url),
+            AttemptStatus::Failed { .. } | AttemptStatus::Cancelled => format!("Retrying {}", url),
+        });
+        // An explicit re-download bypasses the duplicate check
+        self.enqueue(url, true);
+    }
+
+    /// Re-queues every failed or cancelled attempt in the current view
+    fn retry_failed(&mut self) {
+        let urls = match self.library.failed_urls(&self.history_filter) {
+            Ok(urls) => urls,
+            Err(e) => {
+                self.notification = Some(format!("Failed to load history: {}", e));
+                return;
+            }
+        };
+
+        self.notification = Some(format!("Retrying {} failed download{}", urls.len(), if urls.len() == 1 { "" } else { "s" }));
+        for url in urls {
+            self.enqueue(url, true);
+        }
+    }
+
+    fn copy_selected_url(&mut self) {
+        let Some(url) = self.selected_download().map(|d| d.url.clone()) else {
+            return;
+        };
+
+        self.notification = Some(match platform::copy_to_clipboard(&url) {
+            Ok(()) => "Copied URL to clipboard".to_string(),
+            Err(e) => format!("Failed to copy URL: {}", e),
+        });
+    }
+
+    fn open_selected(&mut self, reveal: bool) {
+        let Some(path) = self.selected_download()
+            .filter(|d| !d.filename.is_empty())
+            .map(|d| PathBuf::from(&d.filename))
+        else {
+            return;
+        };
+
+        if !path.exists() {
+            self.notification = Some(format!("File no longer exists: {}", path.display()));
+            return;
+        }
+
+        let result = if reveal {
+            platform::reveal_path(&path)
+        } else {
+            platform::open_path(&path)
+        };
+        if let Err(e) = result {
+            self.notification = Some(e.to_string());
+        }
+    }
+
+    fn request_delete(&mut self, delete_file: bool) {
+        if let Some(download) = self.selected_download() {
+            self.confirm = Some(Confirm::DeleteEntry {
+                id: download.id,
+                filename: download.filename.clone(),
+                delete_file,
+            });
+        }
+    }
+
+    fn delete_entry(&mut self, id: i64, filename: &str, delete_file: bool) {
+        if let Err(e) = self.library.delete_attempt(id) {
+            self.notification = Some(format!("Failed to delete entry: {}", e));
+            return;
+        }
+
+        self.notification = Some(if delete_file && !filename.is_empty() {
+            match std::fs::remove_file(filename) {
+                Ok(()) => format!("Deleted entry and file {}", filename),
+                Err(e) => format!("Deleted entry, but could not remove file: {}", e),
+            }
+        } else {
+            "Deleted history entry".to_string()
+        });
+
+        self.refresh_history();
     }
 
     pub fn handle_mouse_click(&mut self, x: u16, _y: u16, area: FocusedArea) {
         match area {
             FocusedArea::Tabs => {
                 self.focused_area = FocusedArea::Tabs;
-                // Assuming tabs are at the top and each tab is roughly half the width
-                if x < (termsize::get().map(|s| s.cols).unwrap_or(80) / 2) {
-                    self.selected_tab = 0;
-                } else {
-                    self.selected_tab = 1;
+                // Assuming tabs are at the top and each tab is roughly a quarter of the width
+                let width = termsize::get().map(|s| s.cols).unwrap_or(80);
+                self.selected_tab = (x / (width / 4).max(1)).min(3) as usize;
+                if self.selected_tab == 3 {
+                    self.run_diagnostics();
                 }
             }
             FocusedArea::Input => {
                 self.focused_area = FocusedArea::Input;
                 self.enter_edit_mode();
             }
+            FocusedArea::History => {
+                self.focused_area = FocusedArea::History;
+                self.selected_tab = 1;
+            }
             FocusedArea::ExitButton => {
                 self.focused_area = FocusedArea::ExitButton;
             }
@@ -0,0 +1,70 @@
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Borders, BorderType, Clear, Paragraph},
+    Frame,
+};
+
+use crate::ui::app::Confirm;
+use crate::ui::styles::{THEME_SECONDARY, THEME_TEXT, THEME_ERROR, THEME_PRIMARY};
+
+pub fn render_confirm(frame: &mut Frame, confirm: &Confirm, area: Rect) {
+    let (title, question, answers, border) = match confirm {
+        Confirm::DeleteEntry { delete_file: true, .. } => (
+            " ⚠️ Confirm ",
+            "Delete this entry AND its file from disk?".to_string(),
+            "y = yes · any other key = no",
+            THEME_ERROR,
+        ),
+        Confirm::DeleteEntry { delete_file: false, .. } => (
+            " ⚠️ Confirm ",
+            "Delete this history entry?".to_string(),
+            "y = yes · any other key = no",
+            THEME_ERROR,
+        ),
+        Confirm::Export => (
+            " 📤 Export ",
+            "Export the entries shown to the reports folder as:".to_string(),
+            "c = CSV · j = JSON Lines · h = HTML report",
+            THEME_ERROR,
+        ),
+        Confirm::Clipboard(urls) if urls.len() == 1 => (
+            " 📋 Clipboard ",
+            format!("Download {}?", urls[0]),
+            "y = yes · any other key = no",
+            THEME_PRIMARY,
+        ),
+        Confirm::Clipboard(urls) => (
+            " 📋 Clipboard ",
+            format!("Download {} copied Instagram links?", urls.len()),
+            "y = yes · any other key = no",
+            THEME_PRIMARY,
+        ),
+    };
+
+    let width = (question.chars().count() as u16 + 6).min(area.width);
+    let popup = Rect {
+        x: area.x + area.width.saturating_sub(width) / 2,
+        y: area.y + area.height.saturating_sub(5) / 2,
+        width,
+        height: 5.min(area.height),
+    };
+
+    let dialog = Paragraph::new(vec![
+        Line::from(question),
+        Line::from(Span::styled(answers, Style::default().fg(THEME_SECONDARY))),
+    ])
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(THEME_TEXT))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title(title)
+                .border_style(Style::default().fg(border))
+        );
+
+    frame.render_widget(Clear, popup);
+    frame.render_widget(dialog, popup);
+}
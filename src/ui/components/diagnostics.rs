@@ -0,0 +1,61 @@
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Style, Modifier},
+    text::{Line, Span},
+    widgets::{Block, Borders, BorderType, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::diagnostics::CheckStatus;
+use crate::ui::app::App;
+use crate::ui::styles::{THEME_PRIMARY, THEME_SECONDARY, THEME_TEXT, THEME_WARNING, THEME_ERROR, THEME_SUCCESS};
+
+pub fn render_diagnostics_tab(frame: &mut Frame, app: &App, area: Rect) {
+    let footer = match &app.diagnostics {
+        _ if app.diagnosing() => " checking… ".to_string(),
+        Some(report) => format!(" checked {} · r to check again · e save report · c copy report ", report.finished.format("%H:%M:%S")),
+        None => " r to check ".to_string(),
+    };
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title(" 🩺 Diagnostics ")
+        .title_bottom(Line::from(footer).alignment(Alignment::Right))
+        .border_style(Style::default().fg(THEME_PRIMARY));
+
+    if let Some(notification) = &app.notification {
+        block = block.title(
+            Line::styled(format!(" {} ", notification), Style::default().fg(THEME_WARNING))
+                .alignment(Alignment::Right)
+        );
+    }
+
+    let Some(report) = &app.diagnostics else {
+        let waiting = Paragraph::new("Running checks…").style(Style::default().fg(THEME_TEXT)).block(block);
+        frame.render_widget(waiting, area);
+        return;
+    };
+
+    let lines: Vec<Line> = report
+        .checks
+        .iter()
+        .map(|check| {
+            let color = match check.status {
+                CheckStatus::Ok => THEME_SUCCESS,
+                CheckStatus::Warning => THEME_WARNING,
+                CheckStatus::Failed => THEME_ERROR,
+            };
+            Line::from(vec![
+                Span::raw(format!("{} ", check.status.symbol())),
+                Span::styled(
+                    format!("{:<18}", check.name),
+                    Style::default().fg(THEME_SECONDARY).add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(check.detail.clone(), Style::default().fg(color)),
+            ])
+        })
+        .collect();
+
+    let checks = Paragraph::new(lines).block(block).wrap(Wrap { trim: false });
+    frame.render_widget(checks, area);
+}
@@ -1,18 +1,20 @@
 use ratatui::{
     layout::{Rect, Alignment},
     style::Style,
-    text::Text,
+    text::{Line, Text},
     widgets::{Block, Borders, BorderType, Paragraph, Wrap},
     Frame,
 };
 
+use crate::core::disk::format_size;
+use crate::core::schedule::format_countdown;
 use crate::ui::app::{App, DownloadStatus};
 use crate::ui::styles::{THEME_SECONDARY, THEME_PRIMARY, THEME_WARNING, THEME_SUCCESS, THEME_ERROR, THEME_TEXT};
 
 pub fn render_download_tab(frame: &mut Frame, app: &App, area: Rect) {
     let (status_text, style) = match &app.download_status {
         DownloadStatus::None => (
-            "✨ Press 'i' to enter URL, 'Enter' to download, or click to interact".to_string(),
+            "✨ Press 'i' to enter URL, 'Enter' to download (add ' @01:00-06:00' to defer it), or click to interact".to_string(),
             Style::default().fg(THEME_TEXT)
         ),
         DownloadStatus::InProgress => (
@@ -21,7 +23,7 @@ pub fn render_download_tab(frame: &mut Frame, app: &App, area: Rect) {
         ),
         DownloadStatus::Downloading { progress, speed, eta, size } => (
             format!(
-                "⬇️ Downloading... {:.1}%\n📊 Speed: {}\n⏱️ ETA: {}\n📦 Size: {}",
+                "⬇️ Downloading... {:.1}%\n📊 Speed: {}\n⏱️ ETA: {}\n📦 Size: {}\n⏹️ Press 'x' to cancel",
                 progress * 100.0,
                 speed,
                 eta,
@@ -39,8 +41,92 @@ pub fn render_download_tab(frame: &mut Frame, app: &App, area: Rect) {
         ),
     };
 
-    let status = Paragraph::new(Text::from(status_text))
-        .style(style)
+    let mut text = Text::styled(status_text, style);
+
+    if let Some(job) = app.queue.current() {
+        text.lines.insert(0, Line::styled(
+            format!("🔗 Job #{}: {}", job.id, job.url),
+            Style::default().fg(THEME_TEXT)
+        ));
+    }
+
+    if !app.queue.is_empty() {
+        text.lines.push(Line::styled(
+            format!("📥 Queued: {}", app.queue.len()),
+            Style::default().fg(THEME_TEXT)
+        ));
+    }
+
+    if let Some(until) = app.queue.waiting_until() {
+        text.lines.push(Line::styled(
+            format!(
+                "⏸️ Paused for quiet hours or a job window, next start at {} (in {})",
+                until.format("%H:%M"),
+                format_countdown(until, chrono::Local::now())
+            ),
+            Style::default().fg(THEME_WARNING)
+        ));
+    }
+
+    if let Some(wait) = app.queue.throttled_for() {
+        text.lines.push(Line::styled(
+            format!("🐢 Rate limited, next job in {}s", wait.as_secs() + 1),
+            Style::default().fg(THEME_WARNING)
+        ));
+    }
+
+    if let Some((free, needed)) = app.queue.low_on_space() {
+        text.lines.push(Line::styled(
+            format!("💾 Paused: only {} free, waiting until there is {}", format_size(free), format_size(needed)),
+            Style::default().fg(THEME_WARNING)
+        ));
+    }
+
+    if app.config.settings.storage.quota_mb.is_some() {
+        text.lines.push(Line::styled(
+            format!("💾 Storage: {}", app.config.settings.storage.summary()),
+            Style::default().fg(THEME_TEXT)
+        ));
+    }
+
+    if let Some(limits) = app.config.settings.rate_limit.summary() {
+        text.lines.push(Line::styled(
+            format!("🚦 Limits: {}", limits),
+            Style::default().fg(THEME_TEXT)
+        ));
+    }
+
+    if let Some((healthy, total)) = app.queue.proxy_health() {
+        let (line, color) = if healthy == total {
+            (format!("🌐 Rotating through {} proxies", total), THEME_TEXT)
+        } else {
+            (format!("🌐 Proxies: {} of {} usable, the rest are resting after a rate limit", healthy, total), THEME_WARNING)
+        };
+        text.lines.push(Line::styled(line, Style::default().fg(color)));
+    }
+
+    if let Some(clipboard) = &app.clipboard {
+        text.lines.push(Line::styled(
+            format!("📎 Watching the {} clipboard for Instagram links", clipboard.provider()),
+            Style::default().fg(THEME_TEXT)
+        ));
+    }
+
+    if let Some(warning) = app.config.settings.tls.warning() {
+        text.lines.insert(0, Line::styled(
+            format!("⚠️ {}", warning),
+            Style::default().fg(THEME_ERROR)
+        ));
+    }
+
+    if let Some(notification) = &app.notification {
+        text.lines.insert(0, Line::styled(
+            format!("📋 {}", notification),
+            Style::default().fg(THEME_WARNING)
+        ));
+    }
+
+    let status = Paragraph::new(text)
         .block(
             Block::default()
                 .borders(Borders::ALL)
@@ -1,21 +1,49 @@
 use ratatui::{
-    layout::Rect,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Style, Modifier},
     text::{Line, Span},
-    widgets::{Block, Borders, BorderType, List, ListItem},
+    widgets::{Block, Borders, BorderType, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
 
-use crate::ui::app::{App, FocusedArea};
-use crate::ui::styles::{THEME_PRIMARY, THEME_SECONDARY, THEME_ACCENT, THEME_TEXT, THEME_WARNING};
+use crate::core::disk::format_size;
+use crate::core::history::HistoryPrompt;
+use crate::ui::app::{App, AttemptStatus, FocusedArea};
+use crate::ui::editor::LineEditor;
+use crate::ui::styles::{THEME_PRIMARY, THEME_SECONDARY, THEME_ACCENT, THEME_TEXT, THEME_WARNING, THEME_ERROR, THEME_SUCCESS};
 
 pub fn render_history_tab(frame: &mut Frame, app: &App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(60),  // List
+            Constraint::Percentage(40),  // Details
+        ])
+        .split(area);
+
+    render_history_list(frame, app, chunks[0]);
+    render_history_details(frame, app, chunks[1]);
+}
+
+fn render_history_list(frame: &mut Frame, app: &App, area: Rect) {
+    let area = match &app.history_prompt {
+        Some((prompt, editor)) => {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(3), Constraint::Min(0)])
+                .split(area);
+            render_prompt(frame, *prompt, editor, chunks[0]);
+            chunks[1]
+        }
+        None => area,
+    };
+
     let items: Vec<ListItem> = app
-        .downloads
+        .history
         .iter()
         .enumerate()
         .map(|(i, download)| {
-            let style = if Some(i) == app.selected_history_item {
+            let style = if Some(app.history_offset + i) == app.selected_history_item {
                 Style::default()
                     .fg(THEME_ACCENT)
                     .add_modifier(Modifier::BOLD)
@@ -30,27 +58,156 @@ pub fn render_history_tab(frame: &mut Frame, app: &App, area: Rect) {
                 ),
                 Span::raw(" "),
                 Span::styled(
-                    format!("📹 {}", download.filename),
+                    match download.status {
+                        AttemptStatus::Completed if download.removed => format!("🗑️ {}", file_name(&download.filename)),
+                        AttemptStatus::Completed => format!("📹 {}", file_name(&download.filename)),
+                        AttemptStatus::Failed { .. } => format!("❌ {}", download.url),
+                        AttemptStatus::Cancelled => format!("⏹️ {}", download.url),
+                        AttemptStatus::SkippedDuplicate => format!("⏭️ {}", download.url),
+                    },
                     style
                 ),
             ]))
         })
         .collect();
 
+    let title = if app.history_filter.is_active() {
+        format!(" 📜 Download History ({} matching) ", app.history_len)
+    } else {
+        format!(" 📜 Download History ({}) ", app.history_len)
+    };
+
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title(title)
+        .title_bottom(format!(" {} ", app.history_filter.summary()))
+        .title_bottom(
+            Line::from(" / search · t status · w/W date · p/P profile · s/S sort · x reset ")
+                .alignment(Alignment::Right)
+        )
+        .border_style(
+            if matches!(app.focused_area, FocusedArea::History | FocusedArea::Tabs) && app.selected_tab == 1 {
+                Style::default().fg(THEME_PRIMARY)
+            } else {
+                Style::default().fg(THEME_SECONDARY)
+            }
+        );
+
+    if let Some(notification) = &app.notification {
+        block = block.title(
+            Line::styled(format!(" {} ", notification), Style::default().fg(THEME_WARNING))
+                .alignment(Alignment::Right)
+        );
+    }
+
     let history = List::new(items)
+        .block(block)
+        .highlight_symbol("▶ ");
+
+    // The list scrolls itself to keep the selection in view
+    let selected = app.selected_history_item.and_then(|i| i.checked_sub(app.history_offset));
+    let mut state = ListState::default().with_selected(selected);
+    frame.render_stateful_widget(history, area, &mut state);
+}
+
+fn render_prompt(frame: &mut Frame, prompt: HistoryPrompt, editor: &LineEditor, area: Rect) {
+    let (symbol, title) = match prompt {
+        HistoryPrompt::Search => ("/ ", " 🔎 Search URL, file, uploader, caption "),
+        HistoryPrompt::Profile => ("@ ", " 👤 Uploader (empty for all) "),
+        HistoryPrompt::Dates => ("📅", " 📅 From..to, YYYY-MM-DD (either may be left out) "),
+    };
+    let (visible, cursor_col) = editor.visible(area.width.saturating_sub(4) as usize);
+    let prompt = Paragraph::new(Line::from(vec![
+        Span::styled(symbol, Style::default().fg(THEME_SECONDARY)),
+        Span::styled(visible, Style::default().fg(THEME_ACCENT)),
+    ]))
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
-                .title(" 📜 Download History ")
-                .border_style(
-                    if matches!(app.focused_area, FocusedArea::Tabs) && app.selected_tab == 1 {
-                        Style::default().fg(THEME_PRIMARY)
-                    } else {
-                        Style::default().fg(THEME_SECONDARY)
-                    }
-                )
+                .title(title)
+                .border_style(Style::default().fg(THEME_PRIMARY))
         );
 
-    frame.render_widget(history, area);
-} 
\ No newline at end of file
+    frame.render_widget(prompt, area);
+    frame.set_cursor(area.x + cursor_col as u16 + 3, area.y + 1);
+}
+
+fn render_history_details(frame: &mut Frame, app: &App, area: Rect) {
+    let label = Style::default().fg(THEME_SECONDARY).add_modifier(Modifier::BOLD);
+    let value = Style::default().fg(THEME_TEXT);
+
+    let lines = match app.selected_download() {
+        Some(download) => vec![
+            Line::from(Span::styled("🔗 URL", label)),
+            Line::from(Span::styled(download.url.as_str(), value)),
+            Line::default(),
+            Line::from(Span::styled("📹 File", label)),
+            if download.removed {
+                Line::from(Span::styled(format!("{} (deleted by the storage quota)", download.filename), value.fg(THEME_WARNING)))
+            } else {
+                Line::from(Span::styled(download.filename.as_str(), value))
+            },
+            Line::default(),
+            Line::from(vec![
+                Span::styled("📅 Date ", label),
+                Span::styled(download.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(), value),
+            ]),
+            Line::from(vec![
+                Span::styled("📌 Status ", label),
+                Span::styled(
+                    download.status.to_string(),
+                    match download.status {
+                        AttemptStatus::Completed => value.fg(THEME_SUCCESS),
+                        AttemptStatus::Failed { .. } => value.fg(THEME_ERROR),
+                        AttemptStatus::Cancelled | AttemptStatus::SkippedDuplicate => value.fg(THEME_WARNING),
+                    }
+                ),
+            ]),
+            Line::from(vec![
+                Span::styled("⏱️ Took ", label),
+                Span::styled(format!("{:.1}s", download.duration_ms as f64 / 1000.0), value),
+            ]),
+            Line::from(vec![
+                Span::styled("📦 Size ", label),
+                Span::styled(download.size.map(format_size).unwrap_or_else(|| "-".to_string()), value),
+            ]),
+            Line::from(vec![
+                Span::styled("🔒 SHA-256 ", label),
+                Span::styled(download.sha256.as_deref().unwrap_or("-"), value),
+            ]),
+            Line::from(vec![
+                Span::styled("👤 Uploader ", label),
+                Span::styled(download.uploader.as_deref().unwrap_or("-"), value),
+            ]),
+            Line::default(),
+            Line::from(Span::styled("📝 Caption", label)),
+            Line::from(Span::styled(download.caption.as_deref().unwrap_or("-"), value)),
+            Line::default(),
+            Line::from(Span::styled("⚠️ Error", label)),
+            Line::from(Span::styled(download.error.as_deref().unwrap_or("-"), value.fg(THEME_ERROR))),
+        ],
+        None => vec![Line::from(Span::styled("No download selected", value))],
+    };
+
+    let details = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title(" 🔍 Details ")
+                .title_bottom(" r redownload/retry · R retry all failed · c copy · o open · f folder · d/D delete · e export ")
+                .border_style(Style::default().fg(THEME_SECONDARY))
+        )
+        .wrap(Wrap { trim: false });
+
+    frame.render_widget(details, area);
+}
+
+fn file_name(path: &str) -> &str {
+    std::path::Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path)
+}
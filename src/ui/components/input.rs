@@ -10,7 +10,8 @@ use crate::ui::styles::{THEME_PRIMARY, THEME_SECONDARY, THEME_ACCENT, THEME_TEXT
 
 pub fn render_input(frame: &mut Frame, app: &App, area: Rect) {
     let is_focused = matches!(app.focused_area, FocusedArea::Input);
-    let input = Paragraph::new(app.input.as_str())
+    let (visible, cursor_col) = app.input.visible(area.width.saturating_sub(2) as usize);
+    let input = Paragraph::new(visible)
         .style(
             if matches!(app.input_mode, InputMode::Editing) {
                 Style::default().fg(THEME_ACCENT)
@@ -36,7 +37,7 @@ pub fn render_input(frame: &mut Frame, app: &App, area: Rect) {
 
     if let InputMode::Editing = app.input_mode {
         frame.set_cursor(
-            area.x + app.input.len() as u16 + 1,
+            area.x + cursor_col as u16 + 1,
             area.y + 1,
         );
     }
@@ -3,9 +3,15 @@ mod input;
 mod download;
 mod history;
 mod exit_button;
+mod confirm;
+mod subscriptions;
+mod diagnostics;
 
 pub use tabs::render_tabs;
 pub use input::render_input;
 pub use download::render_download_tab;
 pub use history::render_history_tab;
-pub use exit_button::render_exit_button; 
\ No newline at end of file
+pub use exit_button::render_exit_button;
+pub use confirm::render_confirm;
+pub use subscriptions::render_subscriptions_tab;
+pub use diagnostics::render_diagnostics_tab; 
\ No newline at end of file
@@ -0,0 +1,111 @@
+use chrono::{DateTime, Local};
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Style, Modifier},
+    text::{Line, Span},
+    widgets::{Block, Borders, BorderType, List, ListItem, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::core::schedule::format_countdown;
+use crate::ui::app::{App, QueueBackend};
+use crate::ui::styles::{THEME_PRIMARY, THEME_SECONDARY, THEME_TEXT, THEME_WARNING, THEME_ERROR, THEME_SUCCESS};
+
+pub fn render_subscriptions_tab(frame: &mut Frame, app: &App, area: Rect) {
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title(format!(" 🔔 Subscriptions ({}) ", app.config.settings.subscriptions.len()))
+        .border_style(Style::default().fg(THEME_PRIMARY));
+
+    if let QueueBackend::Remote(_) = app.queue {
+        block = block.title_bottom(Line::from(" checked by the daemon · Tab to refresh ").alignment(Alignment::Right));
+    }
+
+    if let Some(notification) = &app.notification {
+        block = block.title(
+            Line::styled(format!(" {} ", notification), Style::default().fg(THEME_WARNING))
+                .alignment(Alignment::Right)
+        );
+    }
+
+    if app.config.settings.subscriptions.is_empty() {
+        let help = Paragraph::new(vec![
+            Line::from("No subscriptions yet. Add profiles or hashtags to config.toml:"),
+            Line::default(),
+            Line::styled("[[subscriptions]]", Style::default().fg(THEME_SECONDARY)),
+            Line::styled("profile = \"natgeo\"", Style::default().fg(THEME_SECONDARY)),
+            Line::styled("interval_minutes = 60", Style::default().fg(THEME_SECONDARY)),
+        ])
+            .style(Style::default().fg(THEME_TEXT))
+            .block(block)
+            .wrap(Wrap { trim: false });
+        frame.render_widget(help, area);
+        return;
+    }
+
+    let checking = app.subscriptions.as_ref().and_then(|poller| poller.checking());
+    let now = Local::now();
+
+    let items: Vec<ListItem> = app
+        .config
+        .settings
+        .subscriptions
+        .iter()
+        .map(|subscription| {
+            let key = subscription.key();
+            let state = app.subscription_states.iter().find(|state| state.key == key);
+
+            let (status, style) = match state {
+                _ if checking == Some(key.as_str()) => ("🔄 checking now".to_string(), Style::default().fg(THEME_WARNING)),
+                Some(state) if state.last_error.is_some() => (
+                    format!(
+                        "❌ {} ({} failure{})",
+                        state.last_error.as_deref().unwrap_or_default(),
+                        state.failures,
+                        if state.failures == 1 { "" } else { "s" }
+                    ),
+                    Style::default().fg(THEME_ERROR),
+                ),
+                Some(state) => (
+                    format!("✅ {} new on last check", state.last_found),
+                    Style::default().fg(THEME_SUCCESS),
+                ),
+                None => ("⏳ not checked yet".to_string(), Style::default().fg(THEME_TEXT)),
+            };
+
+            let checked = state
+                .and_then(|state| state.last_checked)
+                .map(|time| format!("checked {}", time.format("%b %d %H:%M")))
+                .unwrap_or_else(|| "never checked".to_string());
+            let next = match state {
+                Some(state) if state.next_check > now => format!("next {}", until(state.next_check, now)),
+                _ => "next check due".to_string(),
+            };
+
+            ListItem::new(vec![
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:<24}", subscription.label()),
+                        Style::default().fg(THEME_PRIMARY).add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(status, style),
+                ]),
+                Line::styled(
+                    format!(
+                        "   every {} min · last {} posts · {} · {}",
+                        subscription.interval_minutes, subscription.limit, checked, next
+                    ),
+                    Style::default().fg(THEME_SECONDARY),
+                ),
+            ])
+        })
+        .collect();
+
+    frame.render_widget(List::new(items).block(block), area);
+}
+
+/// "in 1h 05m 09s (14:30)"
+fn until(time: DateTime<Local>, now: DateTime<Local>) -> String {
+    format!("in {} ({})", format_countdown(time, now), time.format("%H:%M"))
+}
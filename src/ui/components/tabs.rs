@@ -7,10 +7,10 @@ use ratatui::{
 };
 
 use crate::ui::app::{App, FocusedArea};
-use crate::ui::styles::{THEME_PRIMARY, THEME_SECONDARY, THEME_ACCENT, THEME_TEXT};
+use crate::ui::styles::{THEME_PRIMARY, THEME_SECONDARY, THEME_ACCENT, THEME_ERROR, THEME_TEXT};
 
 pub fn render_tabs(frame: &mut Frame, app: &App, area: Rect) {
-    let titles: Vec<Line> = vec!["📥 Download", "📋 History"]
+    let titles: Vec<Line> = ["📥 Download", "📋 History", "🔔 Subscriptions", "🩺 Diagnostics"]
         .iter()
         .map(|t| {
             Line::from(vec![
@@ -24,8 +24,14 @@ pub fn render_tabs(frame: &mut Frame, app: &App, area: Rect) {
         })
         .collect();
 
+    // Stays in sight on every tab
+    let mut block = Block::default().title(" Tabs ");
+    if !app.config.settings.tls.verify {
+        block = block.title(Span::styled(" ⚠️ TLS verification off ", Style::default().fg(THEME_ERROR).add_modifier(Modifier::BOLD)));
+    }
+
     let tabs = Tabs::new(titles)
-        .block(Block::default()
+        .block(block
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded)
             .border_style(
@@ -34,8 +40,7 @@ pub fn render_tabs(frame: &mut Frame, app: &App, area: Rect) {
                 } else {
                     Style::default().fg(THEME_SECONDARY)
                 }
-            )
-            .title(" Tabs "))
+            ))
         .select(app.selected_tab)
         .highlight_style(
             Style::default()
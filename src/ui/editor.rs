@@ -0,0 +1,294 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Single-line text buffer backing the URL input box and the History tab's
+/// prompts.
+///
+/// The cursor is kept as a byte offset that always sits on a grapheme
+/// boundary, so multi-byte characters and emoji move as one unit.
+#[derive(Default)]
+pub struct LineEditor {
+    text: String,
+    cursor: usize,
+    history: Vec<String>,
+    history_index: Option<usize>,
+    draft: String,
+}
+
+impl LineEditor {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Applies the readline-style bindings shared by every text field
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+
+        match key.code {
+            KeyCode::Char('u') if ctrl => self.delete_to_start(),
+            KeyCode::Char('w') if ctrl => self.delete_word_before(),
+            KeyCode::Char('a') if ctrl => self.move_home(),
+            KeyCode::Char('e') if ctrl => self.move_end(),
+            KeyCode::Char('b') if alt => self.move_word_left(),
+            KeyCode::Char('f') if alt => self.move_word_right(),
+            KeyCode::Char(c) if !ctrl => self.insert_char(c),
+            KeyCode::Left if ctrl || alt => self.move_word_left(),
+            KeyCode::Right if ctrl || alt => self.move_word_right(),
+            KeyCode::Left => self.move_left(),
+            KeyCode::Right => self.move_right(),
+            KeyCode::Home => self.move_home(),
+            KeyCode::End => self.move_end(),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Up => self.history_prev(),
+            KeyCode::Down => self.history_next(),
+            _ => {}
+        }
+    }
+
+    /// Returns the current line and resets the editor, remembering the line
+    /// for later recall with Up/Down.
+    pub fn submit(&mut self) -> String {
+        let line = std::mem::take(&mut self.text);
+        self.cursor = 0;
+        self.history_index = None;
+
+        if !line.trim().is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        line
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    pub fn insert_str(&mut self, s: &str) {
+        let s: String = s.chars().filter(|c| !c.is_control()).collect();
+        self.text.insert_str(self.cursor, &s);
+        self.cursor += s.len();
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.prev_boundary(self.cursor);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = self.next_boundary(self.cursor);
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    pub fn move_word_left(&mut self) {
+        self.cursor = self.prev_word_start(self.cursor);
+    }
+
+    pub fn move_word_right(&mut self) {
+        self.cursor = self.text[self.cursor..]
+            .split_word_bound_indices()
+            .map(|(i, word)| (self.cursor + i + word.len(), word))
+            .find(|(_, word)| is_word(word))
+            .map(|(end, _)| end)
+            .unwrap_or(self.text.len());
+    }
+
+    pub fn backspace(&mut self) {
+        let start = self.prev_boundary(self.cursor);
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    pub fn delete(&mut self) {
+        let end = self.next_boundary(self.cursor);
+        self.text.replace_range(self.cursor..end, "");
+    }
+
+    /// Ctrl+U: drop everything left of the cursor
+    pub fn delete_to_start(&mut self) {
+        self.text.replace_range(..self.cursor, "");
+        self.cursor = 0;
+    }
+
+    /// Ctrl+W: drop the word left of the cursor
+    pub fn delete_word_before(&mut self) {
+        let start = self.prev_word_start(self.cursor);
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    pub fn history_prev(&mut self) {
+        if self.history.is_empty() {
+            return;
+        }
+
+        let index = match self.history_index {
+            None => {
+                self.draft = self.text.clone();
+                self.history.len() - 1
+            }
+            Some(0) => 0,
+            Some(i) => i - 1,
+        };
+        self.history_index = Some(index);
+        self.set_text(self.history[index].clone());
+    }
+
+    pub fn history_next(&mut self) {
+        match self.history_index {
+            None => {}
+            Some(i) if i + 1 < self.history.len() => {
+                self.history_index = Some(i + 1);
+                self.set_text(self.history[i + 1].clone());
+            }
+            Some(_) => {
+                self.history_index = None;
+                let draft = std::mem::take(&mut self.draft);
+                self.set_text(draft);
+            }
+        }
+    }
+
+    /// Returns the slice of text that fits in `width` columns together with
+    /// the cursor's column inside that slice, scrolling horizontally so the
+    /// cursor always stays visible.
+    pub fn visible(&self, width: usize) -> (&str, usize) {
+        let width = width.max(1);
+        let cursor_col = self.text[..self.cursor].width();
+
+        if cursor_col < width {
+            let end = self.take_width(0, width);
+            return (&self.text[..end], cursor_col);
+        }
+
+        // Walk back from the cursor until the window is full
+        let mut start = self.cursor;
+        let mut used = 0;
+        for (i, grapheme) in self.text[..self.cursor].grapheme_indices(true).rev() {
+            let w = grapheme.width();
+            if used + w >= width {
+                break;
+            }
+            used += w;
+            start = i;
+        }
+
+        let end = self.take_width(start, width);
+        (&self.text[start..end], used)
+    }
+
+    fn set_text(&mut self, text: String) {
+        self.text = text;
+        self.cursor = self.text.len();
+    }
+
+    fn take_width(&self, start: usize, width: usize) -> usize {
+        let mut used = 0;
+        for (i, grapheme) in self.text[start..].grapheme_indices(true) {
+            used += grapheme.width();
+            if used > width {
+                return start + i;
+            }
+        }
+        self.text.len()
+    }
+
+    fn prev_boundary(&self, pos: usize) -> usize {
+        self.text[..pos]
+            .grapheme_indices(true)
+            .next_back()
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
+    fn next_boundary(&self, pos: usize) -> usize {
+        self.text[pos..]
+            .graphemes(true)
+            .next()
+            .map(|g| pos + g.len())
+            .unwrap_or(pos)
+    }
+
+    fn prev_word_start(&self, pos: usize) -> usize {
+        self.text[..pos]
+            .split_word_bound_indices()
+            .rev()
+            .find(|(_, word)| is_word(word))
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+}
+
+/// URLs are full of punctuation; only alphanumeric runs count as words when
+/// jumping so `/`, `.` and `?` act as separators.
+fn is_word(segment: &str) -> bool {
+    segment.chars().any(char::is_alphanumeric)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(text: &str) -> LineEditor {
+        let mut editor = LineEditor::default();
+        editor.insert_str(text);
+        editor
+    }
+
+    #[test]
+    fn moves_over_whole_graphemes() {
+        let mut editor = editor("a👍🏽é");
+        editor.backspace();
+        assert_eq!(editor.as_str(), "a👍🏽");
+        editor.move_left();
+        editor.insert_char('b');
+        assert_eq!(editor.as_str(), "ab👍🏽");
+        editor.delete();
+        assert_eq!(editor.as_str(), "ab");
+    }
+
+    #[test]
+    fn treats_url_punctuation_as_word_breaks() {
+        let mut editor = editor("https://instagram.com/p/ABC");
+        editor.delete_word_before();
+        assert_eq!(editor.as_str(), "https://instagram.com/p/");
+        editor.move_word_left();
+        editor.move_word_left();
+        editor.delete_to_start();
+        assert_eq!(editor.as_str(), "instagram.com/p/");
+    }
+
+    #[test]
+    fn recalls_submitted_lines() {
+        let mut editor = editor("first");
+        assert_eq!(editor.submit(), "first");
+        editor.insert_str("second");
+        editor.submit();
+        editor.insert_str("draft");
+
+        editor.history_prev();
+        assert_eq!(editor.as_str(), "second");
+        editor.history_prev();
+        editor.history_prev();
+        assert_eq!(editor.as_str(), "first");
+        editor.history_next();
+        editor.history_next();
+        assert_eq!(editor.as_str(), "draft");
+    }
+
+    #[test]
+    fn scrolls_to_keep_the_cursor_visible() {
+        let editor = editor("abcdefghij");
+        let (visible, cursor) = editor.visible(4);
+        assert_eq!(visible, "hij");
+        assert_eq!(cursor, 3);
+    }
+}
@@ -1,5 +1,6 @@
 pub mod app;
 pub mod components;
+pub mod editor;
 pub mod styles;
 
 use ratatui::{
@@ -36,6 +37,12 @@ pub fn render(frame: &mut Frame, app: &App) {
     match app.selected_tab {
         0 => render_download_tab(frame, app, chunks[2]),
         1 => render_history_tab(frame, app, chunks[2]),
+        2 => render_subscriptions_tab(frame, app, chunks[2]),
+        3 => render_diagnostics_tab(frame, app, chunks[2]),
         _ => unreachable!(),
     }
+
+    if let Some(confirm) = &app.confirm {
+        render_confirm(frame, confirm, chunks[2]);
+    }
 } 
\ No newline at end of file